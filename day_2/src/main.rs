@@ -1,7 +1,11 @@
 use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
 
+mod repair;
+mod rules;
+
 fn read_file(file_path: &str) -> std::io::Result<String> {
     let mut file = File::open(file_path)?;
     let mut string = String::new();
@@ -11,13 +15,13 @@ fn read_file(file_path: &str) -> std::io::Result<String> {
 
 fn split_string(string: String) -> Option<Vec<String>> {
     let split_string: Vec<String> = string
-        .split("\n")
+        .split('\n')
         .map(|token| token.to_string())
         .collect();
     Some(split_string)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 struct PasswordInfo {
     // In part 2 this is the first index
     min_occurence: usize,
@@ -26,35 +30,204 @@ struct PasswordInfo {
     character: char
 }
 
-fn iterate_lines(lines: &Vec<String>) -> Vec<(usize, usize, char, &str)> {
-    let mut valid_passwords: Vec<(usize, usize, char, &str)> = Vec::new();
-    for line in lines {
-        let mut split_string: Vec<&str> = line
-            .split(|character| character == '-' || character == ':' || character == ' ')
-            .map(|token| token.trim())
-            .collect();
-        split_string.retain(|&token| !token.is_empty());
-
-        if split_string.len() != 4 {
-            continue
+impl fmt::Display for PasswordInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{} {}", self.min_occurence, self.max_occurence, self.character)
+    }
+}
+
+trait PasswordPolicy {
+    fn test(&self, password: &str) -> bool;
+    // Human readable reason behind the verdict of `test`
+    fn explain(&self, password: &str) -> String;
+    // Smallest edit that makes an invalid password valid, if the policy knows one
+    fn repair(&self, _password: &str) -> Option<repair::Repair> {
+        None
+    }
+}
+
+// Part 1: the character must occur between min and max times
+struct SledRentalPolicy {
+    password_info: PasswordInfo
+}
+
+impl PasswordPolicy for SledRentalPolicy {
+    fn test(&self, password: &str) -> bool {
+        test_password(&self.password_info, password)
+    }
+
+    fn explain(&self, password: &str) -> String {
+        explain_password(&self.password_info, password)
+    }
+
+    fn repair(&self, password: &str) -> Option<repair::Repair> {
+        repair::repair_password(&self.password_info, password)
+    }
+}
+
+// Part 2: the character must be at exactly one of the two positions
+struct TobogganPolicy {
+    password_info: PasswordInfo
+}
+
+impl TobogganPolicy {
+    fn new(password_info: PasswordInfo) -> Result<TobogganPolicy, String> {
+        if password_info.min_occurence == 0 || password_info.max_occurence == 0 {
+            return Err(format!("positions are 1-based, got {}-{}", password_info.min_occurence, password_info.max_occurence))
         }
-        let password_info = PasswordInfo {
-            min_occurence: split_string[0].parse()
-                .expect("error"),
-            max_occurence: split_string[1].parse()
-                .expect("error"),
-            character: split_string[2].chars()
-                .next()
-                .expect("Error")
-        };
-        let password = split_string[3];
-
-        if test_password_part_2(&password_info, password) {
-            valid_passwords.push((password_info.min_occurence, password_info.max_occurence, password_info.character, password));
+        Ok(TobogganPolicy { password_info })
+    }
+}
+
+impl PasswordPolicy for TobogganPolicy {
+    fn test(&self, password: &str) -> bool {
+        test_password_part_2(&self.password_info, password)
+    }
+
+    fn explain(&self, password: &str) -> String {
+        explain_password_part_2(&self.password_info, password)
+    }
+
+    fn repair(&self, password: &str) -> Option<repair::Repair> {
+        repair::repair_password_part_2(&self.password_info, password)
+    }
+}
+
+// Constructors validate the password info for their policy before it is used
+type PolicyConstructor = fn(PasswordInfo) -> Result<Box<dyn PasswordPolicy>, String>;
+
+// New policies only need an entry here to become selectable with --policy
+const POLICIES: [(&str, PolicyConstructor); 2] = [
+    ("sled-rental", |password_info| Ok(Box::new(SledRentalPolicy { password_info }))),
+    ("toboggan", |password_info| Ok(Box::new(TobogganPolicy::new(password_info)?))),
+];
+
+fn find_policy(name: &str) -> Option<PolicyConstructor> {
+    POLICIES
+        .iter()
+        .find(|(policy_name, _)| *policy_name == name)
+        .map(|&(_, constructor)| constructor)
+}
+
+type ParsedLine<'a> = Result<(PasswordInfo, &'a str), String>;
+
+fn parse_line(line: &str) -> ParsedLine<'_> {
+    let (policy, password) = line
+        .split_once(": ")
+        .ok_or_else(|| String::from("expected 'min-max c: password'"))?;
+    if password.is_empty() {
+        return Err(String::from("password is empty"))
+    }
+    Ok((parse_password_info(policy)?, password))
+}
+
+fn parse_password_info(policy: &str) -> Result<PasswordInfo, String> {
+    let (range, character) = policy
+        .split_once(' ')
+        .ok_or_else(|| format!("expected 'min-max c', got '{}'", policy))?;
+    let (min_occurence, max_occurence) = range
+        .split_once('-')
+        .ok_or_else(|| format!("expected 'min-max', got '{}'", range))?;
+
+    let mut characters = character.chars();
+    let character = match (characters.next(), characters.next()) {
+        (Some(character), None) => character,
+        _ => return Err(format!("expected a single policy character, got '{}'", character)),
+    };
+
+    Ok(PasswordInfo {
+        min_occurence: min_occurence.parse()
+            .map_err(|_| format!("invalid number '{}'", min_occurence))?,
+        max_occurence: max_occurence.parse()
+            .map_err(|_| format!("invalid number '{}'", max_occurence))?,
+        character
+    })
+}
+
+// Blank lines are skipped, every other line is returned with its 1-based line number
+fn parse_lines(lines: &[String]) -> Vec<(usize, ParsedLine<'_>)> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(line_number, line)| (line_number, parse_line(line)))
+        .collect()
+}
+
+fn iterate_lines(lines: &[String], policy: PolicyConstructor) -> Vec<(usize, usize, char, &str)> {
+    let mut valid_passwords: Vec<(usize, usize, char, &str)> = Vec::new();
+    for (line_number, entry) in parse_lines(lines) {
+        match entry {
+            Ok((password_info, password)) => match policy(password_info) {
+                Ok(policy) => {
+                    if policy.test(password) {
+                        valid_passwords.push((password_info.min_occurence, password_info.max_occurence, password_info.character, password));
+                    }
+                },
+                Err(error) => eprintln!("line {}: {}", line_number, error),
+            },
+            Err(error) => eprintln!("line {}: {}", line_number, error),
         }
     }
 
-    return valid_passwords
+    valid_passwords
+}
+
+fn report_lines(lines: &[String], policy: PolicyConstructor) -> Vec<String> {
+    parse_lines(lines)
+        .into_iter()
+        .map(|(line_number, entry)| match entry {
+            Ok((password_info, password)) => match policy(password_info) {
+                Ok(policy) => {
+                    let verdict = if policy.test(password) { "valid" } else { "invalid" };
+                    format!("line {}: {} {} -> {} ({})", line_number, password_info, password, verdict, policy.explain(password))
+                },
+                Err(error) => format!("line {}: error ({})", line_number, error),
+            },
+            Err(error) => format!("line {}: error ({})", line_number, error),
+        })
+        .collect()
+}
+
+// Suggests a repair for every invalid password, valid ones are left out
+fn repair_lines(lines: &[String], policy: PolicyConstructor) -> Vec<String> {
+    parse_lines(lines)
+        .into_iter()
+        .filter_map(|(line_number, entry)| match entry {
+            Ok((password_info, password)) => match policy(password_info) {
+                Ok(policy) if policy.test(password) => None,
+                Ok(policy) => match policy.repair(password) {
+                    Some(repair) => Some(format!("line {}: {} {} -> {}", line_number, password_info, password, repair)),
+                    None => Some(format!("line {}: {} {} -> no repair possible", line_number, password_info, password)),
+                },
+                Err(error) => Some(format!("line {}: error ({})", line_number, error)),
+            },
+            Err(error) => Some(format!("line {}: error ({})", line_number, error)),
+        })
+        .collect()
+}
+
+// Checks a plain list of passwords, one per line, against every rule of a rule file
+fn report_rules(lines: &[String], rules: &[rules::Rule]) -> Vec<String> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, password)| !password.trim().is_empty())
+        .map(|(index, password)| {
+            let password = password.trim();
+            let failures: Vec<String> = rules
+                .iter()
+                .filter(|rule| !rule.test(password))
+                .map(|rule| format!("rule {}: {}", rule.line_number, rule.explain(password)))
+                .collect();
+            if failures.is_empty() {
+                format!("line {}: {} -> valid", index + 1, password)
+            } else {
+                format!("line {}: {} -> invalid ({})", index + 1, password, failures.join(", "))
+            }
+        })
+        .collect()
 }
 
 fn test_password(password_info: &PasswordInfo, password: &str) -> bool {
@@ -65,27 +238,231 @@ fn test_password(password_info: &PasswordInfo, password: &str) -> bool {
     char_count >= password_info.min_occurence && char_count <= password_info.max_occurence
 }
 
-fn test_password_part_2(password_info: &PasswordInfo, password: &str) -> bool {
-    let chars: Vec<char> = password
+fn explain_password(password_info: &PasswordInfo, password: &str) -> String {
+    let char_count = password
         .chars()
-        .collect();
+        .filter(|&char| char == password_info.character)
+        .count();
+    let times = if char_count == 1 { "time" } else { "times" };
+    format!("'{}' appears {} {}, allowed {}-{}", password_info.character, char_count, times, password_info.min_occurence, password_info.max_occurence)
+}
+
+// Positions are 1-based, a position past the end of the password is absent
+fn character_at(password: &str, position: usize) -> Option<char> {
+    position
+        .checked_sub(1)
+        .and_then(|index| password.chars().nth(index))
+}
+
+fn test_password_part_2(password_info: &PasswordInfo, password: &str) -> bool {
+    let first_character = character_at(password, password_info.min_occurence);
+    let second_character = character_at(password, password_info.max_occurence);
 
-    let first_character = chars[password_info.min_occurence-1];
-    let second_character = chars[password_info.max_occurence-1];
+    (first_character == Some(password_info.character)) != (second_character == Some(password_info.character))
+}
+
+fn explain_password_part_2(password_info: &PasswordInfo, password: &str) -> String {
+    let (first, second) = (password_info.min_occurence, password_info.max_occurence);
+    let describe = |position: usize| match character_at(password, position) {
+        Some(character) => format!("'{}' at position {}", character, position),
+        None => format!("position {} absent", position),
+    };
+    let first_match = character_at(password, first) == Some(password_info.character);
+    let second_match = character_at(password, second) == Some(password_info.character);
 
-    return first_character != second_character && (first_character == password_info.character || second_character == password_info.character)
+    match (first_match, second_match) {
+        (true, true) => format!("'{}' at both positions {} and {}", password_info.character, first, second),
+        (true, false) => format!("'{}' at position {} only, {}", password_info.character, first, describe(second)),
+        (false, true) => format!("'{}' at position {} only, {}", password_info.character, second, describe(first)),
+        (false, false) => format!("'{}' at neither position: {}, {}", password_info.character, describe(first), describe(second)),
+    }
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     let path = &args[1];
 
+    let mut policy_name: Option<&str> = None;
+    let mut report = false;
+    let mut repair = false;
+    let mut rules_path: Option<&str> = None;
+    let mut iter = args.iter().skip(2);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--policy" => policy_name = Some(iter.next().expect("--policy needs a policy name")),
+            "--report" => report = true,
+            "--repair" => repair = true,
+            "--rules" => rules_path = Some(iter.next().expect("--rules needs a file")),
+            _ => panic!("Unknown argument: {}", arg),
+        }
+    }
+    if report && repair {
+        panic!("--report and --repair cannot be combined");
+    }
+    if rules_path.is_some() && (report || repair || policy_name.is_some()) {
+        panic!("--rules cannot be combined with --policy, --report or --repair");
+    }
+
     let input = read_file(path)
         .expect("Error reading file.");
 
     let lines = split_string(input)
         .expect("Error splitting string");
 
-    let result = iterate_lines(&lines);
-    println!("{:?}", result.len());
+    if let Some(rules_path) = rules_path {
+        // With a rule file the input is a plain list of passwords
+        let rules = read_file(rules_path)
+            .expect("Error reading rule file.");
+        let rules = rules::load_rules(&rules)
+            .unwrap_or_else(|error| panic!("Error in rule file: {}", error));
+        for record in report_rules(&lines, &rules) {
+            println!("{}", record);
+        }
+    } else if report || repair {
+        // Reports and repairs are made against a single policy, part 1 unless told otherwise
+        let policy_name = policy_name.unwrap_or(POLICIES[0].0);
+        let policy = find_policy(policy_name)
+            .unwrap_or_else(|| panic!("Unknown policy: {}", policy_name));
+        let records = if repair { repair_lines(&lines, policy) } else { report_lines(&lines, policy) };
+        for record in records {
+            println!("{}", record);
+        }
+    } else if let Some(policy_name) = policy_name {
+        let policy = find_policy(policy_name)
+            .unwrap_or_else(|| panic!("Unknown policy: {}", policy_name));
+        let result = iterate_lines(&lines, policy);
+        println!("{}: {:?}", policy_name, result.len());
+    } else {
+        let result = iterate_lines(&lines, POLICIES[0].1);
+        println!("Part 1: {:?}", result.len());
+        let result = iterate_lines(&lines, POLICIES[1].1);
+        println!("Part 2: {:?}", result.len());
+    }
+}
+
+#[test]
+fn test_find_policy() {
+    assert!(find_policy("sled-rental").is_some());
+    assert!(find_policy("toboggan").is_some());
+    assert!(find_policy("unknown").is_none());
+}
+
+#[test]
+fn test_policies() {
+    let password_info = PasswordInfo { min_occurence: 1, max_occurence: 3, character: 'a' };
+    assert!(SledRentalPolicy { password_info }.test("abcde"));
+    assert!(!SledRentalPolicy { password_info }.test("cdefg"));
+    assert!(SledRentalPolicy { password_info }.test("aaa"));
+    assert!(!SledRentalPolicy { password_info }.test("aaaa"));
+
+    assert!(TobogganPolicy { password_info }.test("abcde"));
+    assert!(!TobogganPolicy { password_info }.test("cdefg"));
+    assert!(!TobogganPolicy { password_info }.test("abade"));
+}
+
+#[test]
+fn test_parse_line() {
+    let (password_info, password) = parse_line("1-3 a: abcde").unwrap();
+    assert_eq!((password_info.min_occurence, password_info.max_occurence, password_info.character, password), (1, 3, 'a', "abcde"));
+    let (password_info, password) = parse_line("16-18 q: qs-q:q q").unwrap();
+    assert_eq!((password_info.min_occurence, password_info.max_occurence, password_info.character, password), (16, 18, 'q', "qs-q:q q"));
+
+    assert_eq!(parse_line("1-3 a abcde").unwrap_err(), "expected 'min-max c: password'");
+    assert_eq!(parse_line("1-3: abcde").unwrap_err(), "expected 'min-max c', got '1-3'");
+    assert_eq!(parse_line("13 a: abcde").unwrap_err(), "expected 'min-max', got '13'");
+    assert_eq!(parse_line("1-x a: abcde").unwrap_err(), "invalid number 'x'");
+    assert_eq!(parse_line("1-3 ab: abcde").unwrap_err(), "expected a single policy character, got 'ab'");
+    assert_eq!(parse_line("1-3 a: ").unwrap_err(), "password is empty");
+}
+
+#[test]
+fn test_report_lines() {
+    let lines: Vec<String> = vec!["1-3 a: abcde", "", "1-3 b: cdefg", "2-9 c: ccccccccc", "garbage"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(report_lines(&lines, POLICIES[0].1), vec![
+        "line 1: 1-3 a abcde -> valid ('a' appears 1 time, allowed 1-3)",
+        "line 3: 1-3 b cdefg -> invalid ('b' appears 0 times, allowed 1-3)",
+        "line 4: 2-9 c ccccccccc -> valid ('c' appears 9 times, allowed 2-9)",
+        "line 5: error (expected 'min-max c: password')",
+    ]);
+    assert_eq!(report_lines(&lines, POLICIES[1].1), vec![
+        "line 1: 1-3 a abcde -> valid ('a' at position 1 only, 'c' at position 3)",
+        "line 3: 1-3 b cdefg -> invalid ('b' at neither position: 'c' at position 1, 'e' at position 3)",
+        "line 4: 2-9 c ccccccccc -> invalid ('c' at both positions 2 and 9)",
+        "line 5: error (expected 'min-max c: password')",
+    ]);
+}
+
+#[test]
+fn test_character_at() {
+    assert_eq!(character_at("abc", 0), None);
+    assert_eq!(character_at("abc", 1), Some('a'));
+    assert_eq!(character_at("abc", 3), Some('c'));
+    assert_eq!(character_at("abc", 4), None);
+    assert_eq!(character_at("", 1), None);
+    assert_eq!(character_at("çaé", 2), Some('a'));
+    assert_eq!(character_at("çaé", 3), Some('é'));
+}
+
+#[test]
+fn test_toboggan_policy_bounds() {
+    let password_info = |min_occurence, max_occurence, character| PasswordInfo { min_occurence, max_occurence, character };
+    assert_eq!(TobogganPolicy::new(password_info(0, 3, 'a')).err(), Some(String::from("positions are 1-based, got 0-3")));
+    assert_eq!(TobogganPolicy::new(password_info(1, 0, 'a')).err(), Some(String::from("positions are 1-based, got 1-0")));
+
+    // A position past the end of the password never holds the character
+    let policy = TobogganPolicy::new(password_info(2, 9, 'a')).unwrap();
+    assert!(policy.test("bab"));
+    assert!(!policy.test("bbb"));
+    assert!(!policy.test("b"));
+    assert_eq!(policy.explain("bab"), "'a' at position 2 only, position 9 absent");
+    assert_eq!(policy.explain("b"), "'a' at neither position: position 2 absent, position 9 absent");
+
+    // Positions count characters, not bytes
+    let policy = TobogganPolicy::new(password_info(2, 3, 'é')).unwrap();
+    assert!(policy.test("çéa"));
+    assert!(policy.test("ççé"));
+    assert!(!policy.test("çéé"));
+    assert!(!policy.test("éaa"));
+    let (password_info, password) = parse_line("1-2 é: éç").unwrap();
+    assert!(TobogganPolicy::new(password_info).unwrap().test(password));
+}
+
+#[test]
+fn test_report_lines_position_zero() {
+    let lines: Vec<String> = vec![String::from("0-2 a: ab")];
+    assert_eq!(report_lines(&lines, POLICIES[0].1), vec!["line 1: 0-2 a ab -> valid ('a' appears 1 time, allowed 0-2)"]);
+    assert_eq!(report_lines(&lines, POLICIES[1].1), vec!["line 1: error (positions are 1-based, got 0-2)"]);
+}
+
+#[test]
+fn test_report_rules() {
+    let rules = rules::load_rules("min-length 6; at-least 1 of [0-9]\nposition 1 is not p").unwrap();
+    let lines: Vec<String> = vec!["hunter2", "", "password", "abc"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(report_rules(&lines, &rules), vec![
+        "line 1: hunter2 -> valid",
+        "line 3: password -> invalid (rule 1: 0 of [0-9] found, at least 1 required, rule 2: 'p' at position 1, must not be 'p')",
+        "line 4: abc -> invalid (rule 1: length 3, minimum 6; 0 of [0-9] found, at least 1 required)",
+    ]);
+}
+
+#[test]
+fn test_repair_lines() {
+    let lines: Vec<String> = vec!["1-3 a: abcde", "1-3 b: cdefg", "2-9 c: ccccccccc", "3-3 a: aaa"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(repair_lines(&lines, POLICIES[0].1), vec![
+        "line 2: 1-3 b cdefg -> cdefgb (distance 1: insert 'b' at position 6)",
+    ]);
+    assert_eq!(repair_lines(&lines, POLICIES[1].1), vec![
+        "line 2: 1-3 b cdefg -> bdefg (distance 1: substitute 'c' with 'b' at position 1)",
+        "line 3: 2-9 c ccccccccc -> cccccccca (distance 1: substitute 'c' with 'a' at position 9)",
+        "line 4: 3-3 a aaa -> no repair possible",
+    ]);
 }
@@ -0,0 +1,212 @@
+use std::fmt;
+use crate::{character_at, PasswordInfo};
+
+// Edits are applied in order. Positions are 1-based and refer to the password as it is
+// when the edit is applied, so an insert can name the position just past the end.
+#[derive(Debug, PartialEq)]
+pub enum Edit {
+    Insert { position: usize, character: char },
+    Delete { position: usize, character: char },
+    Substitute { position: usize, from: char, to: char },
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Edit::Insert { position, character } => write!(f, "insert '{}' at position {}", character, position),
+            Edit::Delete { position, character } => write!(f, "delete '{}' at position {}", character, position),
+            Edit::Substitute { position, from, to } => write!(f, "substitute '{}' with '{}' at position {}", from, to, position),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Repair {
+    pub edits: Vec<Edit>,
+    pub password: String
+}
+
+impl Repair {
+    pub fn distance(&self) -> usize {
+        self.edits.len()
+    }
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let edits: Vec<String> = self.edits
+            .iter()
+            .map(|edit| edit.to_string())
+            .collect();
+        write!(f, "{} (distance {}: {})", self.password, self.distance(), edits.join(", "))
+    }
+}
+
+// Any character that does not satisfy the policy will do where one has to be written
+fn filler_character(password_info: &PasswordInfo) -> char {
+    if password_info.character == 'a' { 'b' } else { 'a' }
+}
+
+// Inserts or deletes the policy character until its count is within bounds.
+// Returns None when the password is already valid or no count can be valid.
+pub fn repair_password(password_info: &PasswordInfo, password: &str) -> Option<Repair> {
+    if password_info.min_occurence > password_info.max_occurence {
+        return None
+    }
+    let mut chars: Vec<char> = password.chars().collect();
+    let char_count = chars
+        .iter()
+        .filter(|&&char| char == password_info.character)
+        .count();
+
+    let mut edits: Vec<Edit> = Vec::new();
+    if char_count < password_info.min_occurence {
+        for _ in char_count..password_info.min_occurence {
+            edits.push(Edit::Insert { position: chars.len() + 1, character: password_info.character });
+            chars.push(password_info.character);
+        }
+    } else if char_count > password_info.max_occurence {
+        // Delete from the end so the positions also match the original password
+        for _ in password_info.max_occurence..char_count {
+            let index = chars
+                .iter()
+                .rposition(|&char| char == password_info.character)?;
+            chars.remove(index);
+            edits.push(Edit::Delete { position: index + 1, character: password_info.character });
+        }
+    }
+
+    if edits.is_empty() {
+        None
+    } else {
+        Some(Repair { edits, password: chars.into_iter().collect() })
+    }
+}
+
+// Makes exactly one of the two positions hold the policy character, extending the
+// password when neither position exists. Returns None when the password is already
+// valid or when both positions are the same, since then no password can be valid.
+pub fn repair_password_part_2(password_info: &PasswordInfo, password: &str) -> Option<Repair> {
+    let character = password_info.character;
+    let first = password_info.min_occurence.min(password_info.max_occurence);
+    let second = password_info.min_occurence.max(password_info.max_occurence);
+    if first == second {
+        return None
+    }
+
+    let first_character = character_at(password, first);
+    let second_character = character_at(password, second);
+    let mut chars: Vec<char> = password.chars().collect();
+    let mut edits: Vec<Edit> = Vec::new();
+
+    match (first_character, second_character) {
+        (Some(first_character), Some(second_character)) if first_character == character && second_character == character => {
+            let to = filler_character(password_info);
+            edits.push(Edit::Substitute { position: second, from: character, to });
+            chars[second - 1] = to;
+        },
+        (Some(first_character), _) if first_character == character => {},
+        (_, Some(second_character)) if second_character == character => {},
+        (Some(first_character), _) => {
+            edits.push(Edit::Substitute { position: first, from: first_character, to: character });
+            chars[first - 1] = character;
+        },
+        (None, _) => {
+            while chars.len() + 1 < first {
+                let filler = filler_character(password_info);
+                edits.push(Edit::Insert { position: chars.len() + 1, character: filler });
+                chars.push(filler);
+            }
+            edits.push(Edit::Insert { position: first, character });
+            chars.push(character);
+        },
+    }
+
+    if edits.is_empty() {
+        None
+    } else {
+        Some(Repair { edits, password: chars.into_iter().collect() })
+    }
+}
+
+#[test]
+fn test_repair_password() {
+    let password_info = PasswordInfo { min_occurence: 2, max_occurence: 3, character: 'a' };
+    assert_eq!(repair_password(&password_info, "abac"), None);
+    assert_eq!(repair_password(&password_info, "bcd"), Some(Repair {
+        edits: vec![Edit::Insert { position: 4, character: 'a' }, Edit::Insert { position: 5, character: 'a' }],
+        password: String::from("bcdaa")
+    }));
+    assert_eq!(repair_password(&password_info, "aabaaa"), Some(Repair {
+        edits: vec![Edit::Delete { position: 6, character: 'a' }, Edit::Delete { position: 5, character: 'a' }],
+        password: String::from("aaba")
+    }));
+    assert_eq!(repair_password(&password_info, "ééa").unwrap().password, "ééaa");
+
+    let password_info = PasswordInfo { min_occurence: 3, max_occurence: 1, character: 'a' };
+    assert_eq!(repair_password(&password_info, "a"), None);
+}
+
+#[test]
+fn test_repair_password_part_2() {
+    let password_info = PasswordInfo { min_occurence: 1, max_occurence: 3, character: 'a' };
+    assert_eq!(repair_password_part_2(&password_info, "abcde"), None);
+    assert_eq!(repair_password_part_2(&password_info, "cdefg"), Some(Repair {
+        edits: vec![Edit::Substitute { position: 1, from: 'c', to: 'a' }],
+        password: String::from("adefg")
+    }));
+    assert_eq!(repair_password_part_2(&password_info, "abade"), Some(Repair {
+        edits: vec![Edit::Substitute { position: 3, from: 'a', to: 'b' }],
+        password: String::from("abbde")
+    }));
+
+    let password_info = PasswordInfo { min_occurence: 4, max_occurence: 6, character: 'é' };
+    assert_eq!(repair_password_part_2(&password_info, "xé"), Some(Repair {
+        edits: vec![Edit::Insert { position: 3, character: 'a' }, Edit::Insert { position: 4, character: 'é' }],
+        password: String::from("xéaé")
+    }));
+    assert_eq!(repair_password_part_2(&password_info, "xéxxx").unwrap().distance(), 1);
+
+    let password_info = PasswordInfo { min_occurence: 2, max_occurence: 2, character: 'a' };
+    assert_eq!(repair_password_part_2(&password_info, "bb"), None);
+}
+
+#[test]
+fn test_repair_display() {
+    let password_info = PasswordInfo { min_occurence: 1, max_occurence: 3, character: 'a' };
+    assert_eq!(repair_password_part_2(&password_info, "abade").unwrap().to_string(), "abbde (distance 1: substitute 'a' with 'b' at position 3)");
+}
+
+#[cfg(test)]
+fn apply_edits(password: &str, edits: &[Edit]) -> String {
+    let mut chars: Vec<char> = password.chars().collect();
+    for edit in edits {
+        match *edit {
+            Edit::Insert { position, character } => chars.insert(position - 1, character),
+            Edit::Delete { position, character } => assert_eq!(chars.remove(position - 1), character),
+            Edit::Substitute { position, from, to } => {
+                assert_eq!(chars[position - 1], from);
+                chars[position - 1] = to;
+            },
+        }
+    }
+    chars.into_iter().collect()
+}
+
+#[test]
+fn test_edits_replay() {
+    // Applying the edits one after the other gives the repaired password
+    let part_1 = PasswordInfo { min_occurence: 2, max_occurence: 3, character: 'a' };
+    let part_2 = PasswordInfo { min_occurence: 4, max_occurence: 6, character: 'é' };
+    let substitute = PasswordInfo { min_occurence: 1, max_occurence: 3, character: 'a' };
+    let repairs = [
+        ("bcd", repair_password(&part_1, "bcd")),
+        ("aabaaa", repair_password(&part_1, "aabaaa")),
+        ("xé", repair_password_part_2(&part_2, "xé")),
+        ("abade", repair_password_part_2(&substitute, "abade")),
+    ];
+    for (password, repair) in &repairs {
+        let repair = repair.as_ref().unwrap();
+        assert_eq!(apply_edits(password, &repair.edits), repair.password);
+    }
+}
@@ -0,0 +1,296 @@
+// Rule files describe password policies that are loaded at runtime.
+//
+// Every line that is not blank or a '#' comment is a rule. A rule is a list of
+// constraints separated by ';' and a password must meet all of them:
+//
+//   min-length 8; max-length 64
+//   at-least 2 of [0-9]; at-most 3 of [a-z]
+//   position 3 is not x
+//   sled-rental 1-3 a
+//
+// The last form is the password database policy syntax, `min-max c`, prefixed
+// with the name of the policy that interprets it.
+use crate::{character_at, find_policy, parse_password_info, PasswordPolicy};
+
+struct MinLength {
+    length: usize
+}
+
+impl PasswordPolicy for MinLength {
+    fn test(&self, password: &str) -> bool {
+        password.chars().count() >= self.length
+    }
+
+    fn explain(&self, password: &str) -> String {
+        format!("length {}, minimum {}", password.chars().count(), self.length)
+    }
+}
+
+struct MaxLength {
+    length: usize
+}
+
+impl PasswordPolicy for MaxLength {
+    fn test(&self, password: &str) -> bool {
+        password.chars().count() <= self.length
+    }
+
+    fn explain(&self, password: &str) -> String {
+        format!("length {}, maximum {}", password.chars().count(), self.length)
+    }
+}
+
+// A bracketed set of characters and ranges such as [a-z0-9!]
+struct CharacterSet {
+    text: String,
+    ranges: Vec<(char, char)>
+}
+
+impl CharacterSet {
+    fn contains(&self, character: char) -> bool {
+        self.ranges
+            .iter()
+            .any(|&(start, end)| start <= character && character <= end)
+    }
+
+    fn count(&self, password: &str) -> usize {
+        password
+            .chars()
+            .filter(|&character| self.contains(character))
+            .count()
+    }
+}
+
+struct AtLeast {
+    amount: usize,
+    set: CharacterSet
+}
+
+impl PasswordPolicy for AtLeast {
+    fn test(&self, password: &str) -> bool {
+        self.set.count(password) >= self.amount
+    }
+
+    fn explain(&self, password: &str) -> String {
+        format!("{} of {} found, at least {} required", self.set.count(password), self.set.text, self.amount)
+    }
+}
+
+struct AtMost {
+    amount: usize,
+    set: CharacterSet
+}
+
+impl PasswordPolicy for AtMost {
+    fn test(&self, password: &str) -> bool {
+        self.set.count(password) <= self.amount
+    }
+
+    fn explain(&self, password: &str) -> String {
+        format!("{} of {} found, at most {} allowed", self.set.count(password), self.set.text, self.amount)
+    }
+}
+
+struct Position {
+    position: usize,
+    character: char,
+    // Whether the character is required at the position or forbidden there
+    required: bool
+}
+
+impl PasswordPolicy for Position {
+    fn test(&self, password: &str) -> bool {
+        (character_at(password, self.position) == Some(self.character)) == self.required
+    }
+
+    fn explain(&self, password: &str) -> String {
+        let found = match character_at(password, self.position) {
+            Some(character) => format!("'{}' at position {}", character, self.position),
+            None => format!("position {} absent", self.position),
+        };
+        let expected = if self.required { "must be" } else { "must not be" };
+        format!("{}, {} '{}'", found, expected, self.character)
+    }
+}
+
+// A password meets a rule when it meets every policy in it
+pub struct Rule {
+    pub line_number: usize,
+    policies: Vec<Box<dyn PasswordPolicy>>
+}
+
+impl PasswordPolicy for Rule {
+    fn test(&self, password: &str) -> bool {
+        self.policies
+            .iter()
+            .all(|policy| policy.test(password))
+    }
+
+    // Lists every failed constraint, or every constraint when none failed
+    fn explain(&self, password: &str) -> String {
+        let failed: Vec<String> = self.policies
+            .iter()
+            .filter(|policy| !policy.test(password))
+            .map(|policy| policy.explain(password))
+            .collect();
+        if failed.is_empty() {
+            self.policies
+                .iter()
+                .map(|policy| policy.explain(password))
+                .collect::<Vec<String>>()
+                .join("; ")
+        } else {
+            failed.join("; ")
+        }
+    }
+}
+
+fn parse_number(token: &str) -> Result<usize, String> {
+    token
+        .parse()
+        .map_err(|_| format!("invalid number '{}'", token))
+}
+
+fn parse_position(token: &str) -> Result<usize, String> {
+    match parse_number(token)? {
+        0 => Err(String::from("positions are 1-based, got 0")),
+        position => Ok(position),
+    }
+}
+
+fn parse_character(token: &str) -> Result<char, String> {
+    let mut characters = token.chars();
+    match (characters.next(), characters.next()) {
+        (Some(character), None) => Ok(character),
+        _ => Err(format!("expected a single character, got '{}'", token)),
+    }
+}
+
+fn parse_character_set(token: &str) -> Result<CharacterSet, String> {
+    let inner = token
+        .strip_prefix('[')
+        .and_then(|token| token.strip_suffix(']'))
+        .filter(|inner| !inner.is_empty())
+        .ok_or_else(|| format!("expected a character set such as [0-9], got '{}'", token))?;
+
+    let characters: Vec<char> = inner.chars().collect();
+    let mut ranges: Vec<(char, char)> = Vec::new();
+    let mut index = 0;
+    while index < characters.len() {
+        let start = characters[index];
+        if index + 2 < characters.len() && characters[index + 1] == '-' {
+            let end = characters[index + 2];
+            if start > end {
+                return Err(format!("range {}-{} is reversed in '{}'", start, end, token))
+            }
+            ranges.push((start, end));
+            index += 3;
+        } else {
+            ranges.push((start, start));
+            index += 1;
+        }
+    }
+    Ok(CharacterSet { text: token.to_string(), ranges })
+}
+
+fn parse_constraint(constraint: &str) -> Result<Box<dyn PasswordPolicy>, String> {
+    let tokens: Vec<&str> = constraint.split_whitespace().collect();
+    match tokens.as_slice() {
+        ["min-length", length] => Ok(Box::new(MinLength { length: parse_number(length)? })),
+        ["max-length", length] => Ok(Box::new(MaxLength { length: parse_number(length)? })),
+        ["at-least", amount, "of", set] => Ok(Box::new(AtLeast { amount: parse_number(amount)?, set: parse_character_set(set)? })),
+        ["at-most", amount, "of", set] => Ok(Box::new(AtMost { amount: parse_number(amount)?, set: parse_character_set(set)? })),
+        ["position", position, "is", character] => Ok(Box::new(Position {
+            position: parse_position(position)?,
+            character: parse_character(character)?,
+            required: true
+        })),
+        ["position", position, "is", "not", character] => Ok(Box::new(Position {
+            position: parse_position(position)?,
+            character: parse_character(character)?,
+            required: false
+        })),
+        [policy_name, range, character] => {
+            let policy = find_policy(policy_name)
+                .ok_or_else(|| format!("unknown constraint '{}'", constraint))?;
+            policy(parse_password_info(&format!("{} {}", range, character))?)
+        },
+        _ => Err(format!("unknown constraint '{}'", constraint)),
+    }
+}
+
+fn parse_rule(line: &str) -> Result<Vec<Box<dyn PasswordPolicy>>, String> {
+    line
+        .split(';')
+        .map(|constraint| constraint.trim())
+        .filter(|constraint| !constraint.is_empty())
+        .map(parse_constraint)
+        .collect()
+}
+
+pub fn load_rules(input: &str) -> Result<Vec<Rule>, String> {
+    let mut rules: Vec<Rule> = Vec::new();
+    for (index, line) in input.split('\n').enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let policies = parse_rule(line)
+            .map_err(|error| format!("line {}: {}", index + 1, error))?;
+        rules.push(Rule { line_number: index + 1, policies });
+    }
+    Ok(rules)
+}
+
+#[test]
+fn test_parse_character_set() {
+    let set = parse_character_set("[0-9]").unwrap();
+    assert!(set.contains('0') && set.contains('9') && !set.contains('a'));
+    let set = parse_character_set("[a-c!-]").unwrap();
+    assert_eq!(set.ranges, vec![('a', 'c'), ('!', '!'), ('-', '-')]);
+    assert_eq!(set.count("a-b!zz"), 4);
+
+    assert!(parse_character_set("0-9").is_err());
+    assert!(parse_character_set("[]").is_err());
+    assert_eq!(parse_character_set("[9-0]").err(), Some(String::from("range 9-0 is reversed in '[9-0]'")));
+}
+
+#[test]
+fn test_parse_constraint() {
+    assert!(parse_constraint("min-length 8").unwrap().test("12345678"));
+    assert!(!parse_constraint("min-length 8").unwrap().test("1234567"));
+    assert!(parse_constraint("max-length 3").unwrap().test("éèê"));
+    assert!(!parse_constraint("max-length 3").unwrap().test("abcd"));
+    assert!(parse_constraint("at-least 2 of [0-9]").unwrap().test("a1b2"));
+    assert!(!parse_constraint("at-least 2 of [0-9]").unwrap().test("a1bb"));
+    assert!(parse_constraint("at-most 1 of [A-Z]").unwrap().test("Abc"));
+    assert!(!parse_constraint("at-most 1 of [A-Z]").unwrap().test("ABc"));
+    assert!(parse_constraint("position 3 is x").unwrap().test("abx"));
+    assert!(!parse_constraint("position 3 is x").unwrap().test("ab"));
+    assert!(parse_constraint("position 3 is not x").unwrap().test("ab"));
+    assert!(!parse_constraint("position 3 is not x").unwrap().test("abx"));
+    assert!(parse_constraint("sled-rental 1-3 a").unwrap().test("abcde"));
+    assert!(!parse_constraint("toboggan 1-3 a").unwrap().test("abade"));
+
+    assert_eq!(parse_constraint("position 0 is x").err(), Some(String::from("positions are 1-based, got 0")));
+    assert_eq!(parse_constraint("toboggan 0-3 a").err(), Some(String::from("positions are 1-based, got 0-3")));
+    assert_eq!(parse_constraint("min-length eight").err(), Some(String::from("invalid number 'eight'")));
+    assert_eq!(parse_constraint("shorter 1-3 a").err(), Some(String::from("unknown constraint 'shorter 1-3 a'")));
+    assert_eq!(parse_constraint("max-length").err(), Some(String::from("unknown constraint 'max-length'")));
+}
+
+#[test]
+fn test_load_rules() {
+    let rules = load_rules("# strong passwords\n\nmin-length 8; at-least 2 of [0-9]\nposition 3 is not x\n").unwrap();
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].line_number, 3);
+    assert_eq!(rules[1].line_number, 4);
+
+    assert!(rules[0].test("abcdef12"));
+    assert!(!rules[0].test("abcdefg1"));
+    assert_eq!(rules[0].explain("abcdef12"), "length 8, minimum 8; 2 of [0-9] found, at least 2 required");
+    assert_eq!(rules[0].explain("abc1"), "length 4, minimum 8; 1 of [0-9] found, at least 2 required");
+    assert_eq!(rules[1].explain("abx"), "'x' at position 3, must not be 'x'");
+
+    assert_eq!(load_rules("min-length 8\nat-least two of [0-9]").err(), Some(String::from("line 2: invalid number 'two'")));
+}
@@ -4,7 +4,13 @@ use std::io::Read;
 
 const START_X: usize = 0;
 const START_Y: usize = 0;
-const TREE_CHARACTER: char = '#';
+const OPEN_CHARACTER: u8 = b'.';
+const TREE_CHARACTER: u8 = b'#';
+const OPEN_VISITED_CHARACTER: char = 'O';
+const TREE_VISITED_CHARACTER: char = 'X';
+const ANSI_GREEN: &str = "\x1b[32m";
+const ANSI_RED: &str = "\x1b[31m";
+const ANSI_RESET: &str = "\x1b[0m";
 
 fn read_file(file_path: &str) -> std::io::Result<String> {
     let mut file = File::open(file_path)?;
@@ -13,66 +19,555 @@ fn read_file(file_path: &str) -> std::io::Result<String> {
     Ok(string)
 }
 
-fn split_string(string: &String) -> Vec<&str> {
-    let strings: Vec<&str> = string
-        .split("\n")
-        .collect();
-    strings
+// One segment of the map as a flat grid of bytes, row after row
+#[derive(Debug)]
+struct Map {
+    width: usize,
+    height: usize,
+    squares: Vec<u8>
+}
+
+impl Map {
+    fn row(&self, y: usize) -> &[u8] {
+        &self.squares[y * self.width..(y + 1) * self.width]
+    }
+
+    fn is_tree(&self, x: usize, y: usize) -> bool {
+        self.squares[y * self.width + x] == TREE_CHARACTER
+    }
+}
+
+// Every row must be as wide as the first one and hold only open squares and trees.
+// A newline at the end of the input is not an extra row.
+fn parse_map(input: &str) -> Result<Map, String> {
+    let input = input.strip_suffix('\n').unwrap_or(input);
+    let mut width: Option<usize> = None;
+    let mut height = 0;
+    let mut squares: Vec<u8> = Vec::with_capacity(input.len());
+
+    for (index, row) in input.split('\n').enumerate() {
+        let row = row.strip_suffix('\r').unwrap_or(row).as_bytes();
+        if let Some(position) = row.iter().position(|&square| square != OPEN_CHARACTER && square != TREE_CHARACTER) {
+            let character = String::from_utf8_lossy(&row[position..]).chars().next().unwrap_or('?');
+            return Err(format!("row {} has unexpected character '{}' at column {}", index + 1, character, position + 1))
+        }
+        match width {
+            None if row.is_empty() => return Err(String::from("map is empty")),
+            None => width = Some(row.len()),
+            Some(width) if width != row.len() => {
+                return Err(format!("row {} is {} squares wide, expected {}", index + 1, row.len(), width))
+            },
+            Some(_) => {},
+        }
+        squares.extend_from_slice(row);
+        height += 1;
+    }
+
+    Ok(Map { width: width.unwrap_or(0), height, squares })
 }
 
+// A negative move_x moves the toboggan left, wrapping around like moving right does.
+// Returns None when the move would leave the bottom of the map.
 // Returned boolean is whether or not a tree was hit:
 // true => tree hit
 // false => no tree hit
-fn move_tobaggo(start_x: usize, start_y: usize, move_x: usize, move_y: usize, map: &Vec<&str>) -> Option<(usize, usize, bool)> {
-    if start_y + move_y >= map.len() {
-        println!("Illegal move");
+fn move_tobaggo(start_x: usize, start_y: usize, move_x: isize, move_y: usize, map: &Map) -> Option<(usize, usize, bool)> {
+    let new_y = start_y + move_y;
+    if new_y >= map.height {
         None
     } else {
-        let segment_width = map[0].chars().count();
-        let new_x = (start_x + move_x) % segment_width;
-        let new_y = start_y + move_y;
-        let new_target = map[new_y].chars().collect::<Vec<char>>()[new_x];
-        Some((new_x, new_y, new_target == TREE_CHARACTER))
+        let new_x = (start_x as isize + move_x).rem_euclid(map.width as isize) as usize;
+        Some((new_x, new_y, map.is_tree(new_x, new_y)))
     }
 }
 
-fn count_trees(move_x: usize, move_y: usize, map: &Vec<&str>) -> u8 {
-    let mut count: u8 = 0;
+// Counts the trees for all slopes at once, reading every row of the map only once
+fn count_trees_for_slopes(slopes: &[(isize, usize)], map: &Map) -> Vec<u64> {
+    let mut counts: Vec<u64> = vec![0; slopes.len()];
+    let mut positions: Vec<usize> = vec![START_X; slopes.len()];
+    let moves: Vec<usize> = slopes
+        .iter()
+        .map(|&(move_x, _)| move_x.rem_euclid(map.width as isize) as usize)
+        .collect();
+
+    for y in START_Y + 1..map.height {
+        let row = map.row(y);
+        for (index, &(_, move_y)) in slopes.iter().enumerate() {
+            if !(y - START_Y).is_multiple_of(move_y) {
+                continue
+            }
+            positions[index] = (positions[index] + moves[index]) % map.width;
+            if row[positions[index]] == TREE_CHARACTER {
+                counts[index] += 1;
+            }
+        }
+    }
+    counts
+}
+
+// Every landing of the toboggan along a slope. Unlike move_tobaggo's coordinates the
+// x coordinates are not wrapped, so they tell which repetition of the map was reached.
+fn trace_route(move_x: isize, move_y: usize, map: &Map) -> Vec<(isize, usize, bool)> {
+    let mut route: Vec<(isize, usize, bool)> = Vec::new();
+    let mut x = START_X as isize;
     let mut move_result = move_tobaggo(START_X, START_Y, move_x, move_y, map);
 
     while let Some((new_x, new_y, tree_hit)) = move_result {
-        if tree_hit {
-            count += 1;
+        x += move_x;
+        route.push((x, new_y, tree_hit));
+        move_result = move_tobaggo(new_x, new_y, move_x, move_y, map);
+    }
+    route
+}
+
+// Draws the map with the route on it, repeating the map horizontally as far as the route goes
+fn render_route(route: &[(isize, usize, bool)], map: &Map, colour: bool) -> String {
+    let segment_width = map.width as isize;
+    let min_x = route.iter().map(|&(x, _, _)| x).min().unwrap_or(0).min(0);
+    let max_x = route.iter().map(|&(x, _, _)| x).max().unwrap_or(0).max(segment_width - 1);
+    let first_x = min_x.div_euclid(segment_width) * segment_width;
+    let last_x = (max_x.div_euclid(segment_width) + 1) * segment_width;
+
+    // The toboggan lands at most once on every row
+    let mut landings: Vec<Option<(isize, bool)>> = vec![None; map.height];
+    for &(x, y, tree_hit) in route {
+        landings[y] = Some((x, tree_hit));
+    }
+
+    let mut output = String::new();
+    for (y, landing) in landings.iter().enumerate() {
+        let row = map.row(y);
+        for x in first_x..last_x {
+            match *landing {
+                Some((landing_x, true)) if landing_x == x && colour => output.push_str(&format!("{}{}{}", ANSI_RED, TREE_VISITED_CHARACTER, ANSI_RESET)),
+                Some((landing_x, false)) if landing_x == x && colour => output.push_str(&format!("{}{}{}", ANSI_GREEN, OPEN_VISITED_CHARACTER, ANSI_RESET)),
+                Some((landing_x, true)) if landing_x == x => output.push(TREE_VISITED_CHARACTER),
+                Some((landing_x, false)) if landing_x == x => output.push(OPEN_VISITED_CHARACTER),
+                _ => output.push(row[x.rem_euclid(segment_width) as usize] as char),
+            }
         }
-        if new_y + move_y >= map.len() {
-            break
+        output.push('\n');
+    }
+    output
+}
+
+// Fewest trees to reach a square, along with the index of the step and the square it came from
+type DescentSquare = (u64, usize, usize, usize);
+
+#[derive(Debug, PartialEq)]
+struct Descent {
+    trees: u64,
+    // Landings in the same form as trace_route returns them
+    route: Vec<(isize, usize, bool)>
+}
+
+// Finds the route from the start to the bottom row that hits the fewest trees when
+// every move is one of the given steps. Every step moves down, so the rows can be
+// processed top to bottom with each landing's best cost final once its row is reached.
+fn find_descent(steps: &[(isize, usize)], map: &Map) -> Option<Descent> {
+    let mut best: Vec<Vec<Option<DescentSquare>>> = vec![vec![None; map.width]; map.height];
+    best[START_Y][START_X] = Some((0, 0, START_X, START_Y));
+
+    for y in START_Y..map.height {
+        for x in 0..map.width {
+            let trees = match best[y][x] {
+                Some((trees, _, _, _)) => trees,
+                None => continue,
+            };
+            for (step_index, &(move_x, move_y)) in steps.iter().enumerate() {
+                if let Some((new_x, new_y, tree_hit)) = move_tobaggo(x, y, move_x, move_y, map) {
+                    let new_trees = trees + tree_hit as u64;
+                    match best[new_y][new_x] {
+                        Some((current, _, _, _)) if current <= new_trees => {},
+                        _ => best[new_y][new_x] = Some((new_trees, step_index, x, y)),
+                    }
+                }
+            }
         }
-        move_result = move_tobaggo(new_x, new_y, move_x, move_y, map);
     }
-    count
+
+    let last_y = map.height - 1;
+    let (end_x, &(trees, _, _, _)) = best[last_y]
+        .iter()
+        .enumerate()
+        .filter_map(|(x, square)| square.as_ref().map(|square| (x, square)))
+        .min_by_key(|(_, &(trees, _, _, _))| trees)?;
+
+    // Walk back to the start collecting steps, then replay them to get unwrapped x coordinates
+    let mut taken_steps: Vec<usize> = Vec::new();
+    let (mut x, mut y) = (end_x, last_y);
+    while (x, y) != (START_X, START_Y) {
+        let (_, step_index, previous_x, previous_y) = best[y][x]?;
+        taken_steps.push(step_index);
+        x = previous_x;
+        y = previous_y;
+    }
+
+    let mut route: Vec<(isize, usize, bool)> = Vec::new();
+    let (mut wrapped_x, mut unwrapped_x, mut y) = (START_X, START_X as isize, START_Y);
+    for &step_index in taken_steps.iter().rev() {
+        let (move_x, move_y) = steps[step_index];
+        let (new_x, new_y, tree_hit) = move_tobaggo(wrapped_x, y, move_x, move_y, map)?;
+        unwrapped_x += move_x;
+        route.push((unwrapped_x, new_y, tree_hit));
+        wrapped_x = new_x;
+        y = new_y;
+    }
+    Some(Descent { trees, route })
+}
+
+// Parses a slope given as "dx,dy". dx may be negative to move left, dy must move down.
+fn parse_slope(slope: &str) -> Result<(isize, usize), String> {
+    let (move_x, move_y) = slope
+        .split_once(',')
+        .ok_or_else(|| format!("expected 'dx,dy', got '{}'", slope))?;
+    let move_x: isize = move_x.trim().parse()
+        .map_err(|_| format!("invalid dx '{}'", move_x))?;
+    let move_y: usize = move_y.trim().parse()
+        .map_err(|_| format!("invalid dy '{}'", move_y))?;
+    if move_y == 0 {
+        return Err(format!("dy must be at least 1, got '{}'", slope))
+    }
+    Ok((move_x, move_y))
+}
+
+fn multiply_counts(counts: &[u64]) -> Result<u64, String> {
+    counts
+        .iter()
+        .try_fold(1_u64, |product, &count| product.checked_mul(count))
+        .ok_or_else(|| format!("product of tree counts {:?} overflows a 64-bit integer", counts))
+}
+
+// Parses an inclusive range given as "min..max", e.g. "-3..7"
+fn parse_range<T: std::str::FromStr + PartialOrd + Copy>(range: &str) -> Result<(T, T), String> {
+    let (min, max) = range
+        .split_once("..")
+        .ok_or_else(|| format!("expected 'min..max', got '{}'", range))?;
+    let min: T = min.trim().parse()
+        .map_err(|_| format!("invalid number '{}'", min))?;
+    let max: T = max.trim().parse()
+        .map_err(|_| format!("invalid number '{}'", max))?;
+    if min > max {
+        return Err(format!("range '{}' is reversed", range))
+    }
+    Ok((min, max))
+}
+
+// Number of rows the toboggan lands on after leaving the top row
+fn rows_visited(move_y: usize, map: &Map) -> usize {
+    (map.height - 1 - START_Y) / move_y
+}
+
+#[derive(Debug, PartialEq)]
+struct SlopeResult {
+    slope: (isize, usize),
+    trees: u64,
+    rows_visited: usize
+}
+
+// Counts trees for every slope within the bounds that visits at least min_rows rows,
+// ordered from fewest to most trees
+fn search_slopes(dx_range: (isize, isize), dy_range: (usize, usize), min_rows: usize, map: &Map) -> Vec<SlopeResult> {
+    let mut slopes: Vec<(isize, usize)> = Vec::new();
+    // A dy of the map's height or more never leaves the top row
+    let max_y = dy_range.1.min(map.height.saturating_sub(1));
+    for move_y in dy_range.0.max(1)..=max_y {
+        let rows_visited = rows_visited(move_y, map);
+        if rows_visited == 0 || rows_visited < min_rows {
+            continue
+        }
+        for move_x in dx_range.0..=dx_range.1 {
+            slopes.push((move_x, move_y));
+        }
+    }
+
+    let counts = count_trees_for_slopes(&slopes, map);
+    let mut results: Vec<SlopeResult> = slopes
+        .into_iter()
+        .zip(counts)
+        .map(|(slope, trees)| SlopeResult { slope, trees, rows_visited: rows_visited(slope.1, map) })
+        .collect();
+    results.sort_by_key(|result| result.trees);
+    results
+}
+
+// Takes the first n results along with every result tied with the n-th one,
+// numbering them so that tied results share a rank
+fn rank_with_ties<'a>(results: impl Iterator<Item = &'a SlopeResult>, n: usize) -> Vec<(usize, &'a SlopeResult)> {
+    let mut ranked: Vec<(usize, &SlopeResult)> = Vec::new();
+    for (index, result) in results.enumerate() {
+        match ranked.last() {
+            Some(&(rank, last)) if last.trees == result.trees => ranked.push((rank, result)),
+            _ if index >= n => break,
+            _ => ranked.push((index + 1, result)),
+        }
+    }
+    ranked
+}
+
+fn print_ranking(title: &str, ranking: &[(usize, &SlopeResult)]) {
+    println!("{}:", title);
+    for (rank, result) in ranking {
+        println!("{:>4}. {},{}: {:?} trees over {:?} rows", rank, result.slope.0, result.slope.1, result.trees, result.rows_visited);
+    }
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     let path = &args[1];
 
+    let mut slopes: Vec<(isize, usize)> = Vec::new();
+    let mut search = false;
+    let mut descend = false;
+    let mut steps: Vec<(isize, usize)> = Vec::new();
+    let mut render = false;
+    let mut colour = false;
+    let mut dx_range: (isize, isize) = (-10, 10);
+    let mut dy_range: (usize, usize) = (1, 3);
+    let mut top: usize = 5;
+    let mut min_rows: usize = 1;
+    let mut iter = args.iter().skip(2);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--slope" => {
+                let slope = iter.next().expect("Missing value for --slope");
+                slopes.push(parse_slope(slope).unwrap_or_else(|error| panic!("Error parsing slope: {}", error)));
+            },
+            "--search" => search = true,
+            "--descend" => descend = true,
+            "--step" => {
+                let step = iter.next().expect("Missing value for --step");
+                steps.push(parse_slope(step).unwrap_or_else(|error| panic!("Error parsing step: {}", error)));
+            },
+            "--render" => render = true,
+            "--colour" => colour = true,
+            "--dx" => {
+                let range = iter.next().expect("Missing value for --dx");
+                dx_range = parse_range(range).unwrap_or_else(|error| panic!("Error parsing --dx: {}", error));
+            },
+            "--dy" => {
+                let range = iter.next().expect("Missing value for --dy");
+                dy_range = parse_range(range).unwrap_or_else(|error| panic!("Error parsing --dy: {}", error));
+            },
+            "--top" => top = iter.next()
+                .and_then(|top| top.parse().ok())
+                .expect("Expected a number for --top"),
+            "--min-rows" => min_rows = iter.next()
+                .and_then(|min_rows| min_rows.parse().ok())
+                .expect("Expected a number for --min-rows"),
+            _ => panic!("Unknown argument: {}", arg),
+        }
+    }
+    if slopes.is_empty() {
+        slopes = vec![
+            (1, 1),
+            (3, 1),
+            (5, 1),
+            (7, 1),
+            (1, 2)
+        ];
+    }
+
     let input = read_file(path)
         .expect("Error reading file.");
-    let input = split_string(&input);
+    let input = parse_map(&input)
+        .unwrap_or_else(|error| panic!("Error parsing map: {}", error));
 
-    let slopes: Vec<(usize, usize)> = vec![
-        (1, 1),
-        (3, 1),
-        (5, 1),
-        (7, 1),
-        (1, 2)
-    ];
+    if search {
+        let results = search_slopes(dx_range, dy_range, min_rows, &input);
+        print_ranking("Best slopes", &rank_with_ties(results.iter(), top));
+        print_ranking("Worst slopes", &rank_with_ties(results.iter().rev(), top));
+        return
+    }
 
-    let mut result: Vec<u32> = Vec::new();
-    for slope in slopes {
-        let count = count_trees(slope.0, slope.1, &input);
-        result.push(count.into());
+    if descend {
+        if steps.is_empty() {
+            // Straight down, down-left and down-right
+            steps = vec![(0, 1), (-1, 1), (1, 1)];
+        }
+        match find_descent(&steps, &input) {
+            Some(descent) => {
+                println!("Fewest trees: {:?}", descent.trees);
+                let path: Vec<String> = descent.route
+                    .iter()
+                    .map(|&(x, y, _)| format!("{},{}", x, y))
+                    .collect();
+                println!("Path: {}", path.join(" "));
+                if render {
+                    print!("{}", render_route(&descent.route, &input, colour));
+                }
+            },
+            None => println!("The bottom row cannot be reached with the given steps"),
+        }
+        return
+    }
+
+    let result = count_trees_for_slopes(&slopes, &input);
+    for (slope, count) in slopes.iter().zip(&result) {
+        println!("Slope {},{}: {:?}", slope.0, slope.1, count);
+        if render {
+            print!("{}", render_route(&trace_route(slope.0, slope.1, &input), &input, colour));
+        }
     }
 
-    println!("Result: {:?}", result.iter().product::<u32>());
+    match multiply_counts(&result) {
+        Ok(product) => println!("Result: {:?}", product),
+        Err(error) => eprintln!("Error: {}", error),
+    }
+}
+
+#[test]
+fn test_parse_slope() {
+    assert_eq!(parse_slope("3,1"), Ok((3, 1)));
+    assert_eq!(parse_slope("-2, 3"), Ok((-2, 3)));
+    assert_eq!(parse_slope("0,1"), Ok((0, 1)));
+    assert!(parse_slope("3").is_err());
+    assert!(parse_slope("3,0").is_err());
+    assert!(parse_slope("3,-1").is_err());
+    assert!(parse_slope("a,1").is_err());
+}
+
+#[test]
+fn test_count_trees() {
+    let map = parse_map("....\n...#\n..#.\n.#..").unwrap();
+    assert_eq!(count_trees_for_slopes(&[(1, 1)], &map)[0], 1);
+    assert_eq!(count_trees_for_slopes(&[(-1, 1)], &map)[0], 3);
+    assert_eq!(count_trees_for_slopes(&[(3, 1)], &map)[0], 3);
+    assert_eq!(count_trees_for_slopes(&[(0, 2)], &map)[0], 0);
+
+    // More trees than a u8 could hold
+    let map = parse_map(&vec!["#"; 400].join("\n")).unwrap();
+    assert_eq!(count_trees_for_slopes(&[(0, 1)], &map)[0], 399);
+}
+
+#[test]
+fn test_multiply_counts() {
+    assert_eq!(multiply_counts(&[]), Ok(1));
+    assert_eq!(multiply_counts(&[2, 3, 300]), Ok(1800));
+    assert_eq!(multiply_counts(&[u64::MAX, 1]), Ok(u64::MAX));
+    assert!(multiply_counts(&[u64::MAX, 2]).is_err());
+}
+
+#[test]
+fn test_parse_range() {
+    assert_eq!(parse_range::<isize>("-3..7"), Ok((-3, 7)));
+    assert_eq!(parse_range::<usize>("2..2"), Ok((2, 2)));
+    assert!(parse_range::<usize>("3..1").is_err());
+    assert!(parse_range::<usize>("-1..3").is_err());
+    assert!(parse_range::<isize>("3").is_err());
+}
+
+#[test]
+fn test_search_slopes() {
+    let map = parse_map("....\n...#\n..#.\n.#..").unwrap();
+    let results = search_slopes((-1, 1), (1, 3), 1, &map);
+    let summary: Vec<((isize, usize), u64)> = results
+        .iter()
+        .map(|result| (result.slope, result.trees))
+        .collect();
+    assert_eq!(summary, vec![
+        ((0, 1), 0), ((-1, 2), 0), ((0, 2), 0), ((1, 2), 0),
+        ((-1, 3), 0), ((0, 3), 0),
+        ((1, 1), 1), ((1, 3), 1), ((-1, 1), 3),
+    ]);
+
+    // dy of 2 and 3 only reach a single row
+    let results = search_slopes((-1, 1), (1, 3), 2, &map);
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|result| result.rows_visited == 3));
+
+    let results = search_slopes((0, 0), (1, usize::MAX), 1, &map);
+    assert_eq!(results.len(), 3);
+}
+
+#[test]
+fn test_rank_with_ties() {
+    let result = |trees| SlopeResult { slope: (1, 1), trees, rows_visited: 1 };
+    let results = [result(1), result(2), result(2), result(2), result(5)];
+    let ranks = |ranking: Vec<(usize, &SlopeResult)>| ranking
+        .iter()
+        .map(|(rank, result)| (*rank, result.trees))
+        .collect::<Vec<(usize, u64)>>();
+
+    assert_eq!(ranks(rank_with_ties(results.iter(), 0)), vec![]);
+    assert_eq!(ranks(rank_with_ties(results.iter(), 1)), vec![(1, 1)]);
+    assert_eq!(ranks(rank_with_ties(results.iter(), 2)), vec![(1, 1), (2, 2), (2, 2), (2, 2)]);
+    assert_eq!(ranks(rank_with_ties(results.iter().rev(), 2)), vec![(1, 5), (2, 2), (2, 2), (2, 2)]);
+    assert_eq!(ranks(rank_with_ties(results.iter(), 10)).len(), 5);
+}
+
+#[test]
+fn test_trace_route() {
+    let map = parse_map("....\n...#\n..#.\n.#..").unwrap();
+    assert_eq!(trace_route(3, 1, &map), vec![(3, 1, true), (6, 2, true), (9, 3, true)]);
+    assert_eq!(trace_route(-1, 2, &map), vec![(-1, 2, false)]);
+}
+
+#[test]
+fn test_render_route() {
+    let map = parse_map("..#\n#..\n.#.\n..#").unwrap();
+    assert_eq!(render_route(&trace_route(2, 1, &map), &map, false), concat!(
+        "..#..#..#\n",
+        "#.O#..#..\n",
+        ".#..X..#.\n",
+        "..#..#O.#\n",
+    ));
+    assert_eq!(render_route(&trace_route(-1, 1, &map), &map, false), concat!(
+        "..#..#\n",
+        "#.O#..\n",
+        ".X..#.\n",
+        "O.#..#\n",
+    ));
+    assert_eq!(render_route(&trace_route(0, 3, &map), &map, true), concat!(
+        "..#\n",
+        "#..\n",
+        ".#.\n",
+        "\x1b[32mO\x1b[0m.#\n",
+    ));
+}
+
+#[test]
+fn test_find_descent() {
+    let map = parse_map("....\n#.##\n##.#\n.##.").unwrap();
+    let descent = find_descent(&[(0, 1), (-1, 1), (1, 1)], &map).unwrap();
+    assert_eq!(descent, Descent { trees: 0, route: vec![(1, 1, false), (2, 2, false), (3, 3, false)] });
+
+    // Wrapping around to the left is the only tree-free way down
+    let map = parse_map("....\n#...\n...#\n#.#.").unwrap();
+    let descent = find_descent(&[(0, 1)], &map).unwrap();
+    assert_eq!(descent.trees, 2);
+    let descent = find_descent(&[(0, 1), (-1, 1)], &map).unwrap();
+    assert_eq!(descent, Descent { trees: 0, route: vec![(-1, 1, false), (-2, 2, false), (-3, 3, false)] });
+
+    // Steps of two rows can never land on the last row of a four row map
+    assert_eq!(find_descent(&[(1, 2)], &map), None);
+
+    let map = parse_map(".").unwrap();
+    assert_eq!(find_descent(&[(1, 1)], &map), Some(Descent { trees: 0, route: vec![] }));
+}
+
+#[test]
+fn test_parse_map() {
+    let map = parse_map("..#\n#..\n").unwrap();
+    assert_eq!((map.width, map.height), (2 + 1, 2));
+    assert!(map.is_tree(2, 0) && map.is_tree(0, 1) && !map.is_tree(1, 1));
+    assert_eq!(map.row(1), b"#..");
+    assert_eq!(parse_map("..#\r\n#..").unwrap().squares, b"..##..".to_vec());
+
+    assert_eq!(parse_map("").err(), Some(String::from("map is empty")));
+    assert_eq!(parse_map("..#\n#.").err(), Some(String::from("row 2 is 2 squares wide, expected 3")));
+    assert_eq!(parse_map("..#\n\n#..").err(), Some(String::from("row 2 is 0 squares wide, expected 3")));
+    assert_eq!(parse_map("..#\n#é.").err(), Some(String::from("row 2 has unexpected character 'é' at column 2")));
+}
+
+#[test]
+fn test_count_trees_for_slopes() {
+    let map = parse_map("....\n...#\n..#.\n.#..\n#...").unwrap();
+    let slopes = [(1, 1), (-1, 1), (3, 1), (0, 2), (1, 2), (-5, 1)];
+    let counts: Vec<u64> = slopes
+        .iter()
+        .map(|&(move_x, move_y)| trace_route(move_x, move_y, &map).iter().filter(|&&(_, _, tree_hit)| tree_hit).count() as u64)
+        .collect();
+    assert_eq!(count_trees_for_slopes(&slopes, &map), counts);
+    assert_eq!(counts, vec![2, 4, 4, 1, 0, 4]);
 }
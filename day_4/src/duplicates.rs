@@ -0,0 +1,170 @@
+// Finds passports that share a pid across one or more batches. Records whose fields are
+// identical are reported together as duplicates; records that disagree are compared
+// pairwise and every field that differs is listed. Field order does not matter, and
+// records without a pid are ignored.
+use std::collections::HashMap;
+use std::fmt;
+use crate::first_values;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Location<'a> {
+    pub batch: &'a str,
+    pub record: usize
+}
+
+impl fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} record {}", self.batch, self.record)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Difference<'a> {
+    pub key: &'a str,
+    pub first: Option<&'a str>,
+    pub second: Option<&'a str>
+}
+
+impl fmt::Display for Difference<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} vs {}", self.key, self.first.unwrap_or("missing"), self.second.unwrap_or("missing"))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Finding<'a> {
+    Duplicate { pid: &'a str, locations: Vec<Location<'a>> },
+    Conflict { pid: &'a str, first: Location<'a>, second: Location<'a>, differences: Vec<Difference<'a>> },
+}
+
+impl fmt::Display for Finding<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Finding::Duplicate { pid, locations } => {
+                let locations: Vec<String> = locations
+                    .iter()
+                    .map(|location| location.to_string())
+                    .collect();
+                write!(f, "pid {}: exact duplicates in {}", pid, locations.join(", "))
+            },
+            Finding::Conflict { pid, first, second, differences } => {
+                let differences: Vec<String> = differences
+                    .iter()
+                    .map(|difference| difference.to_string())
+                    .collect();
+                write!(f, "pid {}: {} conflicts with {}: {}", pid, first, second, differences.join(", "))
+            },
+        }
+    }
+}
+
+struct Occurrence<'a> {
+    location: Location<'a>,
+    fields: Vec<(&'a str, &'a str)>
+}
+
+fn differences<'a>(first: &[(&'a str, &'a str)], second: &[(&'a str, &'a str)]) -> Vec<Difference<'a>> {
+    let value_in = |fields: &[(&'a str, &'a str)], key: &str| fields
+        .iter()
+        .find(|&&(field, _)| field == key)
+        .map(|&(_, value)| value);
+    let mut keys: Vec<&str> = first.iter().map(|&(key, _)| key).collect();
+    keys.extend(second.iter().map(|&(key, _)| key).filter(|key| value_in(first, key).is_none()));
+    keys
+        .into_iter()
+        .map(|key| Difference { key, first: value_in(first, key), second: value_in(second, key) })
+        .filter(|difference| difference.first != difference.second)
+        .collect()
+}
+
+// Each batch is a name and its records, numbered from 1 like the rest of the day
+pub fn find_duplicates<'a>(batches: &[(&'a str, Vec<&'a str>)]) -> Vec<Finding<'a>> {
+    // Occurrences grouped by pid in order of first appearance
+    let mut groups: Vec<(&str, Vec<Occurrence>)> = Vec::new();
+    // Index of each pid's group
+    let mut group_indices: HashMap<&str, usize> = HashMap::new();
+    for (batch, records) in batches {
+        for (index, record) in records.iter().enumerate() {
+            let fields = first_values(record);
+            let pid = match fields.iter().find(|&&(key, _)| key == "pid") {
+                Some(&(_, pid)) => pid,
+                None => continue,
+            };
+            let occurrence = Occurrence { location: Location { batch, record: index + 1 }, fields };
+            match group_indices.get(pid) {
+                Some(&group) => groups[group].1.push(occurrence),
+                None => {
+                    group_indices.insert(pid, groups.len());
+                    groups.push((pid, vec![occurrence]));
+                },
+            }
+        }
+    }
+
+    let mut findings: Vec<Finding> = Vec::new();
+    for (pid, occurrences) in groups {
+        // Identical records collapse into one version, the first of them stands for the rest
+        let mut versions: Vec<Vec<&Occurrence>> = Vec::new();
+        for occurrence in &occurrences {
+            match versions.iter_mut().find(|version| differences(&version[0].fields, &occurrence.fields).is_empty()) {
+                Some(version) => version.push(occurrence),
+                None => versions.push(vec![occurrence]),
+            }
+        }
+
+        for version in versions.iter().filter(|version| version.len() > 1) {
+            let locations = version
+                .iter()
+                .map(|occurrence| occurrence.location)
+                .collect();
+            findings.push(Finding::Duplicate { pid, locations });
+        }
+        for (index, first) in versions.iter().enumerate() {
+            for second in &versions[index + 1..] {
+                findings.push(Finding::Conflict {
+                    pid,
+                    first: first[0].location,
+                    second: second[0].location,
+                    differences: differences(&first[0].fields, &second[0].fields),
+                });
+            }
+        }
+    }
+    findings
+}
+
+#[test]
+fn test_differences() {
+    let first = [("pid", "1"), ("hgt", "170cm"), ("cid", "2")];
+    let second = [("hgt", "171cm"), ("pid", "1"), ("ecl", "amb")];
+    assert_eq!(differences(&first, &second), vec![
+        Difference { key: "hgt", first: Some("170cm"), second: Some("171cm") },
+        Difference { key: "cid", first: Some("2"), second: None },
+        Difference { key: "ecl", first: None, second: Some("amb") },
+    ]);
+    assert_eq!(differences(&first, &[("cid", "2"), ("pid", "1"), ("hgt", "170cm")]), vec![]);
+}
+
+#[test]
+fn test_find_duplicates() {
+    let batches = vec![
+        ("a", vec!["pid:1 hgt:170cm", "pid:2 ecl:amb", "hgt:180cm"]),
+        ("b", vec!["hgt:170cm pid:1", "pid:2 ecl:blu", "pid:3"]),
+        ("c", vec!["pid:1 hgt:170cm"]),
+    ];
+    let findings = find_duplicates(&batches);
+    assert_eq!(findings, vec![
+        Finding::Duplicate {
+            pid: "1",
+            locations: vec![Location { batch: "a", record: 1 }, Location { batch: "b", record: 1 }, Location { batch: "c", record: 1 }]
+        },
+        Finding::Conflict {
+            pid: "2",
+            first: Location { batch: "a", record: 2 },
+            second: Location { batch: "b", record: 2 },
+            differences: vec![Difference { key: "ecl", first: Some("amb"), second: Some("blu") }]
+        },
+    ]);
+    assert_eq!(findings[0].to_string(), "pid 1: exact duplicates in a record 1, b record 1, c record 1");
+    assert_eq!(findings[1].to_string(), "pid 2: a record 2 conflicts with b record 2: ecl amb vs blu");
+}
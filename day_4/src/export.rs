@@ -0,0 +1,147 @@
+// Structured exports of document batches, one row per document with its validation
+// status. Every key of a document is kept, whether the schema knows it or not. When a
+// key is duplicated its first value is exported and the duplicate shows up as an error.
+use crate::first_values;
+use crate::schema::Schema;
+
+struct ExportRecord<'a> {
+    number: usize,
+    fields: Vec<(&'a str, &'a str)>,
+    errors: Vec<String>
+}
+
+// Numbers documents from 1 in input order and leaves out blank ones
+fn export_records<'a>(records: &[&'a str], schema: &Schema) -> Vec<ExportRecord<'a>> {
+    records
+        .iter()
+        .enumerate()
+        .filter(|(_, record)| !record.trim().is_empty())
+        .map(|(index, record)| {
+            let fields = first_values(record);
+            let errors = schema
+                .validate(record)
+                .iter()
+                .map(|error| error.to_string())
+                .collect();
+            ExportRecord { number: index + 1, fields, errors }
+        })
+        .collect()
+}
+
+fn json_string(string: &str) -> String {
+    let mut escaped = String::from("\"");
+    for character in string.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            character if (character as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", character as u32)),
+            character => escaped.push(character),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+pub fn export_json_lines(records: &[&str], schema: &Schema) -> String {
+    let mut output = String::new();
+    for record in export_records(records, schema) {
+        let fields: Vec<String> = record.fields
+            .iter()
+            .map(|(key, value)| format!("{}:{}", json_string(key), json_string(value)))
+            .collect();
+        let errors: Vec<String> = record.errors
+            .iter()
+            .map(|error| json_string(error))
+            .collect();
+        output.push_str(&format!(
+            "{{\"record\":{},\"valid\":{},\"errors\":[{}],\"fields\":{{{}}}}}\n",
+            record.number,
+            record.errors.is_empty(),
+            errors.join(","),
+            fields.join(",")
+        ));
+    }
+    output
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Columns are the schema's keys followed by any other keys in order of first appearance.
+// Errors are joined into a single column.
+pub fn export_csv(records: &[&str], schema: &Schema) -> String {
+    let records = export_records(records, schema);
+    let mut columns: Vec<&str> = schema.keys().collect();
+    for record in &records {
+        for &(key, _) in &record.fields {
+            if !columns.contains(&key) {
+                columns.push(key);
+            }
+        }
+    }
+
+    let mut header: Vec<String> = vec![String::from("record"), String::from("valid"), String::from("errors")];
+    header.extend(columns.iter().map(|column| csv_field(column)));
+    let mut output = header.join(",");
+    output.push('\n');
+
+    for record in &records {
+        let mut row: Vec<String> = vec![
+            record.number.to_string(),
+            record.errors.is_empty().to_string(),
+            csv_field(&record.errors.join("; ")),
+        ];
+        for column in &columns {
+            let value = record.fields
+                .iter()
+                .find(|&&(key, _)| key == *column)
+                .map_or("", |&(_, value)| value);
+            row.push(csv_field(value));
+        }
+        output.push_str(&row.join(","));
+        output.push('\n');
+    }
+    output
+}
+
+#[test]
+fn test_json_string() {
+    assert_eq!(json_string("plain"), "\"plain\"");
+    assert_eq!(json_string("a\"b\\c\nd\u{1}"), "\"a\\\"b\\\\c\\nd\\u0001\"");
+}
+
+#[test]
+fn test_csv_field() {
+    assert_eq!(csv_field("plain"), "plain");
+    assert_eq!(csv_field("a,b"), "\"a,b\"");
+    assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+}
+
+#[test]
+fn test_export_json_lines() {
+    let schema = crate::schema::parse_schema("required pid\noptional cid number 1-999").unwrap();
+    let records = vec!["pid:1 eye:\"blue\"", "", "cid:1000 cid:2"];
+    assert_eq!(export_json_lines(&records, &schema), concat!(
+        "{\"record\":1,\"valid\":true,\"errors\":[],\"fields\":{\"pid\":\"1\",\"eye\":\"\\\"blue\\\"\"}}\n",
+        "{\"record\":3,\"valid\":false,\"errors\":[\"cid appears more than once\",\"pid is missing\",\"cid:1000 is invalid, expected a number from 1 to 999\"],\"fields\":{\"cid\":\"1000\"}}\n",
+    ));
+}
+
+#[test]
+fn test_export_csv() {
+    let schema = crate::schema::parse_schema("required pid\noptional cid number 1-999").unwrap();
+    let records = vec!["pid:1 eye:blue,green", "cid:1000 hgt:170cm"];
+    assert_eq!(export_csv(&records, &schema), concat!(
+        "record,valid,errors,pid,cid,eye,hgt\n",
+        "1,true,,1,,\"blue,green\",\n",
+        "2,false,\"pid is missing; cid:1000 is invalid, expected a number from 1 to 999\",,1000,,170cm\n",
+    ));
+}
@@ -1,7 +1,13 @@
 use std::env;
+use std::fmt;
 use std::fs::File;
-use std::io::Read;
-use regex::Regex;
+use std::io::{self, BufReader, Read};
+use schema::Schema;
+
+mod duplicates;
+mod export;
+mod reader;
+mod schema;
 
 fn read_file(file_path: &str) -> std::io::Result<String> {
     let mut file = File::open(file_path)?;
@@ -10,63 +16,452 @@ fn read_file(file_path: &str) -> std::io::Result<String> {
     Ok(string)
 }
 
-fn split_input(string: &String) -> Vec<&str> {
-    let strings: Vec<&str> = string
-        .split("\n\n")
-        .collect();
-    strings
+// Splits documents the same way the streaming reader does, so both paths agree
+fn split_input(string: &str) -> Vec<String> {
+    reader::RecordReader::new(string.as_bytes())
+        .collect::<io::Result<Vec<String>>>()
+        .expect("Reading from memory cannot fail")
+}
+
+#[derive(Debug, PartialEq)]
+enum Height {
+    Cm(u32),
+    In(u32),
+}
+
+#[derive(Debug, PartialEq)]
+struct HairColour {
+    red: u8,
+    green: u8,
+    blue: u8,
+}
+
+#[derive(Debug, PartialEq)]
+enum EyeColour {
+    Amber,
+    Blue,
+    Brown,
+    Grey,
+    Green,
+    Hazel,
+    Other,
+}
+
+#[derive(Debug, PartialEq)]
+struct Passport {
+    birth_year: u32,
+    issue_year: u32,
+    expiration_year: u32,
+    height: Height,
+    hair_colour: HairColour,
+    eye_colour: EyeColour,
+    passport_id: String,
+    country_id: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum FieldError {
+    Missing(String),
+    Duplicate(String),
+    Invalid { field: String, value: String, reason: String },
+    Malformed(String),
 }
 
-fn count_valid_passports(passports: &Vec<&str>) -> u8 {
-    let regex = Regex::new(r"(byr|iyr|eyr|hgt|hcl|ecl|pid)").unwrap();
-    let mut count: u8 = 0;
-    for line in passports {
-        let regex_match = regex.captures_iter(line);
-        if regex_match.count() == 7 {
-            count += 1;
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldError::Missing(field) => write!(f, "{} is missing", field),
+            FieldError::Duplicate(field) => write!(f, "{} appears more than once", field),
+            FieldError::Invalid { field, value, reason } => write!(f, "{}:{} is invalid, {}", field, value, reason),
+            FieldError::Malformed(token) => write!(f, "'{}' is not a key:value pair", token),
         }
     }
-    count
-}
-
-fn count_valid_passports_part2(passports: &Vec<&str>) -> u8 {
-    let field_expressions: Vec<Regex> = vec![
-        Regex::new(r"byr:(19[2-9][0-9]|200[0-2])").unwrap(),
-        Regex::new(r"iyr:(201[0-9]|2020)").unwrap(),
-        Regex::new(r"eyr:(202[0-9]|2030)").unwrap(),
-        Regex::new(r"hgt:(((1[5-8][0-9]|19[0-3])cm)|((59|6[0-9]|7[0-6])in))").unwrap(),
-        Regex::new(r"hcl:#[0-9a-fA-F]{6}").unwrap(),
-        Regex::new(r"ecl:(amb|blu|brn|gry|grn|hzl|oth)").unwrap(),
-        Regex::new(r"pid:\d{9}(\D|$)").unwrap(),
-        ];
-
-    let mut valid_passport_count: u8 = 0;
-    for line in passports {
-        let mut valid_field_count = 0;
-        for expression in &field_expressions {
-            if expression.is_match(line) {
-                valid_field_count += 1;
-            }
+}
+
+// Splits a passport record into its key:value pairs, in the order they appear
+fn parse_fields(record: &str) -> Vec<Result<(&str, &str), &str>> {
+    record
+        .split_whitespace()
+        .map(|token| token.split_once(':').ok_or(token))
+        .collect()
+}
+
+// The value of each key the first time it appears, for documents that repeat keys
+fn first_values(record: &str) -> Vec<(&str, &str)> {
+    let mut values: Vec<(&str, &str)> = Vec::new();
+    for (key, value) in parse_fields(record).into_iter().filter_map(|field| field.ok()) {
+        if !values.iter().any(|&(seen, _)| seen == key) {
+            values.push((key, value));
         }
+    }
+    values
+}
+
+// The schema has validated the values by the time they are converted, so these only
+// fail when a custom schema accepts values that do not fit a passport.
+fn parse_year(value: &str) -> Result<u32, String> {
+    value.parse().map_err(|_| String::from("expected a year"))
+}
 
-        if valid_field_count == field_expressions.len() {
-            valid_passport_count += 1;
+fn parse_height(value: &str) -> Result<Height, String> {
+    let (number, unit) = value.split_at(value.find(|character: char| !character.is_ascii_digit()).unwrap_or(value.len()));
+    let number: u32 = number.parse().map_err(|_| String::from("expected a number followed by cm or in"))?;
+    match unit {
+        "cm" => Ok(Height::Cm(number)),
+        "in" => Ok(Height::In(number)),
+        _ => Err(String::from("expected a number followed by cm or in")),
+    }
+}
+
+fn parse_hair_colour(value: &str) -> Result<HairColour, String> {
+    let digits = value
+        .strip_prefix('#')
+        .filter(|digits| digits.len() == 6 && digits.chars().all(|character| character.is_ascii_hexdigit()))
+        .ok_or_else(|| String::from("expected # followed by six hex digits"))?;
+    let channel = |index: usize| u8::from_str_radix(&digits[index..index + 2], 16).unwrap();
+    Ok(HairColour { red: channel(0), green: channel(2), blue: channel(4) })
+}
+
+fn parse_eye_colour(value: &str) -> Result<EyeColour, String> {
+    match value {
+        "amb" => Ok(EyeColour::Amber),
+        "blu" => Ok(EyeColour::Blue),
+        "brn" => Ok(EyeColour::Brown),
+        "gry" => Ok(EyeColour::Grey),
+        "grn" => Ok(EyeColour::Green),
+        "hzl" => Ok(EyeColour::Hazel),
+        "oth" => Ok(EyeColour::Other),
+        _ => Err(String::from("expected one of amb, blu, brn, gry, grn, hzl or oth")),
+    }
+}
+
+// Builds a passport from a record that passes the schema, or lists every problem with it
+fn parse_passport(record: &str, schema: &Schema) -> Result<Passport, Vec<FieldError>> {
+    let mut errors = schema.validate(record);
+    if !errors.is_empty() {
+        return Err(errors)
+    }
+
+    let values: Vec<(&str, &str)> = parse_fields(record)
+        .into_iter()
+        .filter_map(|field| field.ok())
+        .collect();
+    let value_of = |key: &str| values
+        .iter()
+        .find(|&&(field, _)| field == key)
+        .map(|&(_, value)| value);
+    let mut field = |key: &str| {
+        let value = value_of(key);
+        if value.is_none() {
+            errors.push(FieldError::Missing(key.to_string()));
         }
+        value
+    };
+    let (birth_year, issue_year, expiration_year) = (field("byr"), field("iyr"), field("eyr"));
+    let (height, hair_colour, eye_colour, passport_id) = (field("hgt"), field("hcl"), field("ecl"), field("pid"));
+
+    let birth_year = parse_field(&mut errors, "byr", birth_year, parse_year);
+    let issue_year = parse_field(&mut errors, "iyr", issue_year, parse_year);
+    let expiration_year = parse_field(&mut errors, "eyr", expiration_year, parse_year);
+    let height = parse_field(&mut errors, "hgt", height, parse_height);
+    let hair_colour = parse_field(&mut errors, "hcl", hair_colour, parse_hair_colour);
+    let eye_colour = parse_field(&mut errors, "ecl", eye_colour, parse_eye_colour);
+    let passport_id = parse_field(&mut errors, "pid", passport_id, |value| Ok(value.to_string()));
+
+    match (birth_year, issue_year, expiration_year, height, hair_colour, eye_colour, passport_id) {
+        (Some(birth_year), Some(issue_year), Some(expiration_year), Some(height), Some(hair_colour), Some(eye_colour), Some(passport_id)) if errors.is_empty() => Ok(Passport {
+            birth_year,
+            issue_year,
+            expiration_year,
+            height,
+            hair_colour,
+            eye_colour,
+            passport_id,
+            country_id: value_of("cid").map(|value| value.to_string()),
+        }),
+        _ => Err(errors),
     }
-    valid_passport_count
+}
+
+// Parses a field if it is present, recording why when its value is invalid
+fn parse_field<T>(errors: &mut Vec<FieldError>, field: &str, value: Option<&str>, parse: impl Fn(&str) -> Result<T, String>) -> Option<T> {
+    let value = value?;
+    match parse(value) {
+        Ok(parsed) => Some(parsed),
+        Err(reason) => {
+            errors.push(FieldError::Invalid { field: field.to_string(), value: value.to_string(), reason });
+            None
+        },
+    }
+}
+
+// Passports are converted into the typed model, any other document is only validated
+#[derive(Clone, Copy)]
+enum DocumentKind {
+    Passport,
+    Other,
+}
+
+fn check_document(record: &str, schema: &Schema, kind: DocumentKind) -> Vec<FieldError> {
+    match kind {
+        DocumentKind::Passport => parse_passport(record, schema).err().unwrap_or_default(),
+        DocumentKind::Other => schema.validate(record),
+    }
+}
+
+// Part 1 only asks for every required field to be present
+fn has_required_fields(errors: &[FieldError]) -> bool {
+    !errors.iter().any(|error| matches!(error, FieldError::Missing(_)))
+}
+
+fn count_valid_passports(passports: &[&str], schema: &Schema, kind: DocumentKind) -> usize {
+    passports
+        .iter()
+        .filter(|passport| has_required_fields(&check_document(passport, schema, kind)))
+        .count()
+}
+
+fn count_valid_passports_part2(passports: &[&str], schema: &Schema, kind: DocumentKind) -> usize {
+    passports
+        .iter()
+        .filter(|passport| check_document(passport, schema, kind).is_empty())
+        .count()
+}
+
+// Counts both parts in a single pass over documents read one at a time
+fn count_valid_passports_streaming(records: impl Iterator<Item = io::Result<String>>, schema: &Schema, kind: DocumentKind) -> io::Result<(usize, usize)> {
+    let mut counts = (0, 0);
+    for record in records {
+        let errors = check_document(&record?, schema, kind);
+        if has_required_fields(&errors) {
+            counts.0 += 1;
+        }
+        if errors.is_empty() {
+            counts.1 += 1;
+        }
+    }
+    Ok(counts)
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     let path = &args[1];
 
+    let mut explain = false;
+    let mut schema_path: Option<&str> = None;
+    let mut export_format: Option<&str> = None;
+    let mut duplicates = false;
+    let mut stream = false;
+    let mut batch_paths: Vec<&str> = vec![path];
+    let mut iter = args.iter().skip(2);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--explain" => explain = true,
+            "--schema" => schema_path = Some(iter.next().expect("--schema needs a file")),
+            "--export" => export_format = Some(iter.next().expect("--export needs json or csv")),
+            "--duplicates" => duplicates = true,
+            "--stream" => stream = true,
+            "--batch" => batch_paths.push(iter.next().expect("--batch needs a file")),
+            _ => panic!("Unknown argument: {}", arg),
+        }
+    }
+    if batch_paths.len() > 1 && !duplicates {
+        panic!("--batch only adds files to compare, it needs --duplicates");
+    }
+    if duplicates && (stream || explain || export_format.is_some()) {
+        panic!("--duplicates cannot be combined with --stream, --explain or --export");
+    }
+    if stream && (explain || export_format.is_some()) {
+        panic!("--stream only counts valid passports, it cannot be combined with --explain or --export");
+    }
+
+    // Without a schema file the documents are validated as passports
+    let schema = match schema_path {
+        Some(schema_path) => {
+            let schema = read_file(schema_path)
+                .expect("Error reading schema file.");
+            schema::parse_schema(&schema)
+                .unwrap_or_else(|error| panic!("Error in schema file: {}", error))
+        },
+        None => schema::passport_schema(),
+    };
+    let kind = if schema_path.is_some() { DocumentKind::Other } else { DocumentKind::Passport };
+
+    // Compares every batch, the first file included, by pid
+    if duplicates {
+        let inputs: Vec<String> = batch_paths
+            .iter()
+            .map(|path| read_file(path).unwrap_or_else(|error| panic!("Error reading {}: {}", path, error)))
+            .collect();
+        let records: Vec<Vec<String>> = inputs
+            .iter()
+            .map(|input| split_input(input))
+            .collect();
+        let batches: Vec<(&str, Vec<&str>)> = batch_paths
+            .iter()
+            .zip(&records)
+            .map(|(path, records)| (*path, records.iter().map(|record| record.as_str()).collect()))
+            .collect();
+        for finding in duplicates::find_duplicates(&batches) {
+            println!("{}", finding);
+        }
+        return
+    }
+
+    // Only the counts are available when streaming, so memory stays constant
+    if stream {
+        let file = File::open(path)
+            .expect("Error reading file.");
+        let (part1, part2) = count_valid_passports_streaming(reader::RecordReader::new(BufReader::new(file)), &schema, kind)
+            .expect("Error reading file.");
+        println!("part1: {:?}", part1);
+        println!("part2: {:?}", part2);
+        return
+    }
+
     let input = read_file(path)
         .expect("Error reading file.");
-    let input = split_input(&input);
+    let records = split_input(&input);
+    let input: Vec<&str> = records
+        .iter()
+        .map(|record| record.as_str())
+        .collect();
+
+    if let Some(export_format) = export_format {
+        let output = match export_format {
+            "json" => export::export_json_lines(&input, &schema),
+            "csv" => export::export_csv(&input, &schema),
+            _ => panic!("Unknown export format: {}, expected json or csv", export_format),
+        };
+        print!("{}", output);
+        return
+    }
 
-    let count = count_valid_passports(&input);
+    let count = count_valid_passports(&input, &schema, kind);
     println!("part1: {:?}", count);
 
-    let count = count_valid_passports_part2(&input);
+    let count = count_valid_passports_part2(&input, &schema, kind);
     println!("part2: {:?}", count);
+
+    if explain {
+        for (index, passport) in input.iter().enumerate() {
+            let errors = check_document(passport, &schema, kind);
+            if !errors.is_empty() {
+                let errors: Vec<String> = errors
+                    .iter()
+                    .map(|error| error.to_string())
+                    .collect();
+                println!("document {}: {}", index + 1, errors.join("; "));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parse_fields() {
+    assert_eq!(parse_fields(""), vec![]);
+    assert_eq!(parse_fields("byr:1937 iyr:2017\ncid:147"), vec![Ok(("byr", "1937")), Ok(("iyr", "2017")), Ok(("cid", "147"))]);
+    assert_eq!(parse_fields("byr:1937 hgt"), vec![Ok(("byr", "1937")), Err("hgt")]);
+}
+
+#[test]
+fn test_field_parsers() {
+    assert_eq!(parse_year("2002"), Ok(2002));
+    assert!(parse_year("year").is_err());
+
+    assert_eq!(parse_height("60in"), Ok(Height::In(60)));
+    assert_eq!(parse_height("190cm"), Ok(Height::Cm(190)));
+    assert!(parse_height("190").is_err());
+    assert!(parse_height("cm").is_err());
+
+    assert_eq!(parse_hair_colour("#623A2f"), Ok(HairColour { red: 0x62, green: 0x3a, blue: 0x2f }));
+    assert!(parse_hair_colour("623a2f").is_err());
+    assert!(parse_hair_colour("#623a2").is_err());
+    assert!(parse_hair_colour("#+23a2f").is_err());
+    assert!(parse_hair_colour("#62éa2").is_err());
+
+    assert_eq!(parse_eye_colour("brn"), Ok(EyeColour::Brown));
+    assert!(parse_eye_colour("wat").is_err());
+}
+
+#[test]
+fn test_parse_passport() {
+    let schema = schema::passport_schema();
+    assert_eq!(parse_passport("pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980\nhcl:#623a2f", &schema), Ok(Passport {
+        birth_year: 1980,
+        issue_year: 2012,
+        expiration_year: 2030,
+        height: Height::In(74),
+        hair_colour: HairColour { red: 0x62, green: 0x3a, blue: 0x2f },
+        eye_colour: EyeColour::Green,
+        passport_id: String::from("087499704"),
+        country_id: None,
+    }));
+    assert_eq!(parse_passport("eyr:1972 cid:100\nhcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926", &schema), Err(vec![
+        FieldError::Invalid { field: String::from("eyr"), value: String::from("1972"), reason: String::from("expected a number from 2020 to 2030") },
+        FieldError::Invalid { field: String::from("hgt"), value: String::from("170"), reason: String::from("expected a number followed by cm or in") },
+        FieldError::Invalid { field: String::from("pid"), value: String::from("186cm"), reason: String::from("expected a match for [0-9]{9}") },
+    ]));
+    // A duplicated field does not stand in for a missing one
+    assert_eq!(parse_passport("byr:1937 byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd pid:860033327", &schema), Err(vec![
+        FieldError::Duplicate(String::from("byr")),
+        FieldError::Missing(String::from("ecl")),
+    ]));
+
+    // A schema that accepts anything still needs values that fit a passport
+    let schema = schema::parse_schema("required byr\nrequired hgt").unwrap();
+    assert_eq!(parse_passport("byr:old hgt:tall", &schema), Err(vec![
+        FieldError::Missing(String::from("iyr")),
+        FieldError::Missing(String::from("eyr")),
+        FieldError::Missing(String::from("hcl")),
+        FieldError::Missing(String::from("ecl")),
+        FieldError::Missing(String::from("pid")),
+        FieldError::Invalid { field: String::from("byr"), value: String::from("old"), reason: String::from("expected a year") },
+        FieldError::Invalid { field: String::from("hgt"), value: String::from("tall"), reason: String::from("expected a number followed by cm or in") },
+    ]));
+}
+
+#[test]
+fn test_count_valid_passports() {
+    let schema = schema::passport_schema();
+    let passports = vec![
+        "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\nbyr:1937 iyr:2017 cid:147 hgt:183cm",
+        "iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884\nhcl:#cfa07d byr:1929",
+        "hcl:#ae17e1 iyr:2013\neyr:2024\necl:brn pid:760753108 byr:1931\nhgt:179cm",
+        "hcl:#cfa07d eyr:2025 pid:166559648\niyr:2011 ecl:brn hgt:59in",
+        "byr:1937 byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd pid:860033327",
+        "byr:2010 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327",
+    ];
+    assert_eq!(count_valid_passports(&passports, &schema, DocumentKind::Passport), 3);
+    assert_eq!(count_valid_passports_part2(&passports, &schema, DocumentKind::Passport), 2);
+
+    // With a loose schema only the typed model rejects values that do not fit a passport
+    let schema = schema::parse_schema("required byr\nrequired iyr\nrequired eyr\nrequired hgt\nrequired hcl\nrequired ecl\nrequired pid").unwrap();
+    let passports = ["byr:old iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327"];
+    assert_eq!(count_valid_passports_part2(&passports, &schema, DocumentKind::Other), 1);
+    assert_eq!(count_valid_passports_part2(&passports, &schema, DocumentKind::Passport), 0);
+}
+
+#[test]
+fn test_count_valid_passports_streaming() {
+    let schema = schema::passport_schema();
+    let kind = DocumentKind::Passport;
+    // Extra blank lines, a whitespace-only separator and CRLF all separate documents the same way
+    let input = concat!(
+        "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\nbyr:1937 iyr:2017 cid:147 hgt:183cm\n\n",
+        "iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884\nhcl:#cfa07d byr:1929\n\n\n\n",
+        "hcl:#ae17e1 iyr:2013\neyr:2024\necl:brn pid:760753108 byr:1931\nhgt:179cm\n  \n",
+        "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\nbyr:1937 iyr:2017 hgt:183cm\n \t\n",
+        "hcl:#cfa07d eyr:2025 pid:166559648\niyr:2011 ecl:brn hgt:59in\n",
+    );
+    for input in &[input.to_string(), input.replace('\n', "\r\n")] {
+        let records = split_input(input);
+        let records: Vec<&str> = records.iter().map(|record| record.as_str()).collect();
+        assert_eq!(records.len(), 5);
+        let in_memory = (count_valid_passports(&records, &schema, kind), count_valid_passports_part2(&records, &schema, kind));
+
+        let streamed = count_valid_passports_streaming(reader::RecordReader::new(input.as_bytes()), &schema, kind).unwrap();
+        assert_eq!(streamed, in_memory);
+        assert_eq!(streamed, (3, 3));
+    }
 }
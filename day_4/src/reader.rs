@@ -0,0 +1,52 @@
+// Reads documents one at a time so a batch never has to fit in memory. Lines may end in
+// CRLF and any number of blank lines may separate documents.
+use std::io::{self, BufRead};
+
+pub struct RecordReader<R> {
+    input: R,
+    line: String
+}
+
+impl<R: BufRead> RecordReader<R> {
+    pub fn new(input: R) -> RecordReader<R> {
+        RecordReader { input, line: String::new() }
+    }
+}
+
+impl<R: BufRead> Iterator for RecordReader<R> {
+    type Item = io::Result<String>;
+
+    // The lines of a document are joined with '\n' like they are in the file
+    fn next(&mut self) -> Option<io::Result<String>> {
+        let mut record = String::new();
+        loop {
+            self.line.clear();
+            match self.input.read_line(&mut self.line) {
+                Ok(0) => return if record.is_empty() { None } else { Some(Ok(record)) },
+                Ok(_) => {},
+                Err(error) => return Some(Err(error)),
+            }
+            let line = self.line.trim_end_matches(['\n', '\r']);
+            if line.trim().is_empty() {
+                if !record.is_empty() {
+                    return Some(Ok(record))
+                }
+            } else {
+                if !record.is_empty() {
+                    record.push('\n');
+                }
+                record.push_str(line);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_record_reader() {
+    let input = "\r\nbyr:1937 iyr:2017\r\ncid:147\r\n\r\n\r\n  \r\nhgt:183cm\n\npid:1";
+    let records: Vec<String> = RecordReader::new(input.as_bytes())
+        .collect::<io::Result<_>>()
+        .unwrap();
+    assert_eq!(records, vec!["byr:1937 iyr:2017\ncid:147", "hgt:183cm", "pid:1"]);
+    assert_eq!(RecordReader::new("\n\n".as_bytes()).count(), 0);
+}
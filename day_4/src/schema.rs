@@ -0,0 +1,246 @@
+// Schemas list the fields a document of key:value pairs must or may have and the
+// values each field accepts. See passport.schema for the format.
+use regex::Regex;
+use crate::{parse_fields, FieldError};
+
+const PASSPORT_SCHEMA: &str = include_str!("../passport.schema");
+
+#[derive(Debug)]
+enum Constraint {
+    Any,
+    Number { min: u64, max: u64, digits: Option<usize> },
+    Units(Vec<(String, u64, u64)>),
+    OneOf(Vec<String>),
+    Pattern(Regex),
+}
+
+#[derive(Debug)]
+struct FieldRule {
+    key: String,
+    required: bool,
+    constraint: Constraint
+}
+
+#[derive(Debug)]
+pub struct Schema {
+    fields: Vec<FieldRule>
+}
+
+fn parse_bounds(bounds: &str) -> Result<(u64, u64), String> {
+    let (min, max) = bounds
+        .split_once('-')
+        .ok_or_else(|| format!("expected 'min-max', got '{}'", bounds))?;
+    let min: u64 = min.parse().map_err(|_| format!("invalid number '{}'", min))?;
+    let max: u64 = max.parse().map_err(|_| format!("invalid number '{}'", max))?;
+    if min > max {
+        return Err(format!("bounds '{}' are reversed", bounds))
+    }
+    Ok((min, max))
+}
+
+fn parse_constraint(tokens: &[&str]) -> Result<Constraint, String> {
+    match tokens {
+        [] => Ok(Constraint::Any),
+        ["number", bounds] => {
+            let (min, max) = parse_bounds(bounds)?;
+            Ok(Constraint::Number { min, max, digits: None })
+        },
+        ["number", bounds, "digits", digits] => {
+            let (min, max) = parse_bounds(bounds)?;
+            let digits = digits.parse().map_err(|_| format!("invalid number '{}'", digits))?;
+            Ok(Constraint::Number { min, max, digits: Some(digits) })
+        },
+        ["units", units @ ..] if !units.is_empty() => {
+            let units = units
+                .iter()
+                .map(|unit| {
+                    let (unit, bounds) = unit
+                        .split_once(':')
+                        .ok_or_else(|| format!("expected 'unit:min-max', got '{}'", unit))?;
+                    let (min, max) = parse_bounds(bounds)?;
+                    Ok((unit.to_string(), min, max))
+                })
+                .collect::<Result<Vec<(String, u64, u64)>, String>>()?;
+            Ok(Constraint::Units(units))
+        },
+        ["one-of", values @ ..] if !values.is_empty() => {
+            Ok(Constraint::OneOf(values.iter().map(|value| value.to_string()).collect()))
+        },
+        ["pattern", pattern] => {
+            let regex = Regex::new(&format!("^(?:{})$", pattern))
+                .map_err(|error| format!("invalid pattern '{}': {}", pattern, error))?;
+            Ok(Constraint::Pattern(regex))
+        },
+        _ => Err(format!("unknown constraint '{}'", tokens.join(" "))),
+    }
+}
+
+pub fn parse_schema(input: &str) -> Result<Schema, String> {
+    let mut fields: Vec<FieldRule> = Vec::new();
+    for (index, line) in input.split('\n').enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let field = match tokens.as_slice() {
+            ["required", key, constraint @ ..] | ["optional", key, constraint @ ..] => {
+                if fields.iter().any(|field| field.key == *key) {
+                    return Err(format!("line {}: field '{}' is defined twice", index + 1, key))
+                }
+                FieldRule {
+                    key: key.to_string(),
+                    required: tokens[0] == "required",
+                    constraint: parse_constraint(constraint)
+                        .map_err(|error| format!("line {}: {}", index + 1, error))?
+                }
+            },
+            _ => return Err(format!("line {}: expected 'required|optional key [constraint]'", index + 1)),
+        };
+        fields.push(field);
+    }
+    Ok(Schema { fields })
+}
+
+pub fn passport_schema() -> Schema {
+    parse_schema(PASSPORT_SCHEMA).expect("Built-in passport schema is invalid")
+}
+
+fn check_number(value: &str, min: u64, max: u64, digits: Option<usize>) -> Result<u64, String> {
+    let number: u64 = if !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit()) {
+        value.parse().map_err(|_| String::from("expected a number"))?
+    } else {
+        return Err(String::from("expected a number"))
+    };
+    if let Some(digits) = digits {
+        if value.len() != digits {
+            return Err(format!("expected {} digits", digits))
+        }
+    }
+    if number < min || number > max {
+        return Err(format!("expected a number from {} to {}", min, max))
+    }
+    Ok(number)
+}
+
+impl Constraint {
+    fn check(&self, value: &str) -> Result<(), String> {
+        match self {
+            Constraint::Any => Ok(()),
+            Constraint::Number { min, max, digits } => check_number(value, *min, *max, *digits).map(|_| ()),
+            Constraint::Units(units) => {
+                let split = value.find(|character: char| !character.is_ascii_digit()).unwrap_or(value.len());
+                let (number, unit) = value.split_at(split);
+                let unit_names: Vec<&str> = units.iter().map(|(unit, _, _)| unit.as_str()).collect();
+                match units.iter().find(|(name, _, _)| name == unit) {
+                    Some(&(_, min, max)) => check_number(number, min, max, None)
+                        .map(|_| ())
+                        .map_err(|_| format!("expected {} to {} {}", min, max, unit)),
+                    None => Err(format!("expected a number followed by {}", unit_names.join(" or "))),
+                }
+            },
+            Constraint::OneOf(values) => {
+                if values.iter().any(|allowed| allowed == value) {
+                    Ok(())
+                } else {
+                    Err(format!("expected one of {}", values.join(", ")))
+                }
+            },
+            Constraint::Pattern(regex) => {
+                if regex.is_match(value) {
+                    Ok(())
+                } else {
+                    // Strip the anchors added when the pattern was compiled
+                    let pattern = regex.as_str();
+                    Err(format!("expected a match for {}", &pattern[4..pattern.len() - 2]))
+                }
+            },
+        }
+    }
+}
+
+impl Schema {
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.fields
+            .iter()
+            .map(|field| field.key.as_str())
+    }
+
+    // Lists every problem with a document: malformed tokens, duplicated keys and
+    // then missing or invalid fields in schema order. Keys outside the schema are allowed.
+    pub fn validate(&self, record: &str) -> Vec<FieldError> {
+        let mut errors: Vec<FieldError> = Vec::new();
+        let mut values: Vec<(&str, &str)> = Vec::new();
+        for field in parse_fields(record) {
+            match field {
+                Ok((key, _)) if values.iter().any(|&(seen, _)| seen == key) => {
+                    if !errors.contains(&FieldError::Duplicate(key.to_string())) {
+                        errors.push(FieldError::Duplicate(key.to_string()));
+                    }
+                },
+                Ok(field) => values.push(field),
+                Err(token) => errors.push(FieldError::Malformed(token.to_string())),
+            }
+        }
+
+        for field in &self.fields {
+            match values.iter().find(|&&(key, _)| key == field.key) {
+                Some(&(_, value)) => {
+                    if let Err(reason) = field.constraint.check(value) {
+                        errors.push(FieldError::Invalid { field: field.key.clone(), value: value.to_string(), reason });
+                    }
+                },
+                None if field.required => errors.push(FieldError::Missing(field.key.clone())),
+                None => {},
+            }
+        }
+        errors
+    }
+}
+
+#[test]
+fn test_parse_schema() {
+    let schema = passport_schema();
+    assert_eq!(schema.fields.len(), 8);
+    assert!(schema.fields.iter().filter(|field| field.required).count() == 7);
+
+    assert_eq!(parse_schema("required byr\nmandatory iyr").err(), Some(String::from("line 2: expected 'required|optional key [constraint]'")));
+    assert_eq!(parse_schema("required byr number 2002-1920").err(), Some(String::from("line 1: bounds '2002-1920' are reversed")));
+    assert_eq!(parse_schema("required byr\noptional byr").err(), Some(String::from("line 2: field 'byr' is defined twice")));
+    assert_eq!(parse_schema("required byr colour").err(), Some(String::from("line 1: unknown constraint 'colour'")));
+    assert!(parse_schema("required hcl pattern #[0-9").is_err());
+}
+
+#[test]
+fn test_constraints() {
+    let constraint = parse_constraint(&["number", "1920-2002", "digits", "4"]).unwrap();
+    assert!(constraint.check("2002").is_ok());
+    assert_eq!(constraint.check("2003"), Err(String::from("expected a number from 1920 to 2002")));
+    assert_eq!(constraint.check("01999"), Err(String::from("expected 4 digits")));
+    assert_eq!(constraint.check("-1"), Err(String::from("expected a number")));
+
+    let constraint = parse_constraint(&["units", "cm:150-193", "in:59-76"]).unwrap();
+    assert!(constraint.check("60in").is_ok());
+    assert!(constraint.check("190cm").is_ok());
+    assert_eq!(constraint.check("190in"), Err(String::from("expected 59 to 76 in")));
+    assert_eq!(constraint.check("190"), Err(String::from("expected a number followed by cm or in")));
+
+    let constraint = parse_constraint(&["one-of", "amb", "blu"]).unwrap();
+    assert!(constraint.check("blu").is_ok());
+    assert_eq!(constraint.check("wat"), Err(String::from("expected one of amb, blu")));
+
+    let constraint = parse_constraint(&["pattern", "[0-9]{9}"]).unwrap();
+    assert!(constraint.check("000000001").is_ok());
+    assert_eq!(constraint.check("0123456789"), Err(String::from("expected a match for [0-9]{9}")));
+}
+
+#[test]
+fn test_validate_other_documents() {
+    // Any batch of key:value documents can be checked once it has a schema
+    let schema = parse_schema("required pid pattern [0-9]{9}\nrequired ecl one-of amb blu\nrequired cid number 1-999").unwrap();
+    assert_eq!(schema.validate("pid:000000001 ecl:amb cid:12 extra:kept"), vec![]);
+    assert_eq!(schema.validate("pid:000000001 ecl:grn"), vec![
+        FieldError::Invalid { field: String::from("ecl"), value: String::from("grn"), reason: String::from("expected one of amb, blu") },
+        FieldError::Missing(String::from("cid")),
+    ]);
+}
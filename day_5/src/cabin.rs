@@ -0,0 +1,108 @@
+// The cabin as a grid of seats, occupied by the decoded boarding passes.
+// Free blocks are runs of free seats next to each other in the same row.
+use crate::layout::Layout;
+
+pub struct Cabin {
+    columns: u32,
+    occupied: Vec<bool>
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FreeBlock {
+    pub row: u32,
+    pub first_column: u32,
+    pub last_column: u32
+}
+
+impl Cabin {
+    // Seat ids outside the plane are ignored, decoding has already rejected them
+    pub fn new(seat_ids: &[u32], layout: &Layout) -> Cabin {
+        let mut occupied = vec![false; (layout.rows() * layout.columns()) as usize];
+        for &seat_id in seat_ids {
+            if let Some(seat) = occupied.get_mut(seat_id as usize) {
+                *seat = true;
+            }
+        }
+        Cabin { columns: layout.columns(), occupied }
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &[bool]> {
+        self.occupied.chunks(self.columns as usize)
+    }
+
+    // One line per row: the row number, '#' for occupied and '.' for free seats,
+    // then how many seats of the row are taken
+    pub fn render(&self) -> String {
+        let width = (self.occupied.len() as u32 / self.columns).saturating_sub(1).to_string().len();
+        let mut output = format!("{:width$} ", "", width = width);
+        output.extend((0..self.columns).map(|column| std::char::from_digit(column % 10, 10).unwrap()));
+        output.push('\n');
+        for (row, (seats, occupancy)) in self.rows().zip(self.row_occupancy()).enumerate() {
+            let seats: String = seats
+                .iter()
+                .map(|&occupied| if occupied { '#' } else { '.' })
+                .collect();
+            output.push_str(&format!("{:>width$} {} {}/{}\n", row, seats, occupancy, self.columns, width = width));
+        }
+        output
+    }
+
+    pub fn row_occupancy(&self) -> Vec<u32> {
+        self.rows()
+            .map(count_occupied)
+            .collect()
+    }
+
+    pub fn free_seats(&self) -> Vec<u32> {
+        (0..self.occupied.len() as u32)
+            .filter(|&seat_id| !self.occupied[seat_id as usize])
+            .collect()
+    }
+
+    pub fn free_blocks(&self) -> Vec<FreeBlock> {
+        let mut blocks: Vec<FreeBlock> = Vec::new();
+        for (row, seats) in self.rows().enumerate() {
+            let mut first_column: Option<u32> = None;
+            for (column, &occupied) in seats.iter().enumerate() {
+                match (occupied, first_column) {
+                    (false, None) => first_column = Some(column as u32),
+                    (true, Some(first)) => {
+                        blocks.push(FreeBlock { row: row as u32, first_column: first, last_column: column as u32 - 1 });
+                        first_column = None;
+                    },
+                    _ => {},
+                }
+            }
+            if let Some(first) = first_column {
+                blocks.push(FreeBlock { row: row as u32, first_column: first, last_column: self.columns - 1 });
+            }
+        }
+        blocks
+    }
+}
+
+fn count_occupied(seats: &[bool]) -> u32 {
+    seats.iter().filter(|&&occupied| occupied).count() as u32
+}
+
+#[test]
+fn test_cabin() {
+    let layout = Layout { row_bits: 2, column_bits: 2, ..Layout::default() };
+    let cabin = Cabin::new(&[1, 2, 4, 5, 6, 7, 13], &layout);
+    assert_eq!(cabin.render(), concat!(
+        "  0123\n",
+        "0 .##. 2/4\n",
+        "1 #### 4/4\n",
+        "2 .... 0/4\n",
+        "3 .#.. 1/4\n",
+    ));
+    assert_eq!(cabin.row_occupancy(), vec![2, 4, 0, 1]);
+    assert_eq!(cabin.free_seats(), vec![0, 3, 8, 9, 10, 11, 12, 14, 15]);
+    assert_eq!(cabin.free_blocks(), vec![
+        FreeBlock { row: 0, first_column: 0, last_column: 0 },
+        FreeBlock { row: 0, first_column: 3, last_column: 3 },
+        FreeBlock { row: 2, first_column: 0, last_column: 3 },
+        FreeBlock { row: 3, first_column: 0, last_column: 0 },
+        FreeBlock { row: 3, first_column: 2, last_column: 3 },
+    ]);
+}
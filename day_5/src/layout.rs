@@ -0,0 +1,106 @@
+// The geometry of a plane: how many characters pick the row and the column and which
+// letters pick the lower and upper half. Layout files have one line per part:
+//   row-bits 7 FB
+//   column-bits 3 LR
+// Blank lines and lines starting with '#' are ignored, a missing part keeps its default.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Layout {
+    pub row_bits: u32,
+    pub column_bits: u32,
+    pub row_letters: (char, char),
+    pub column_letters: (char, char)
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout { row_bits: 7, column_bits: 3, row_letters: ('F', 'B'), column_letters: ('L', 'R') }
+    }
+}
+
+impl Layout {
+    pub fn columns(&self) -> u32 {
+        1 << self.column_bits
+    }
+
+    pub fn rows(&self) -> u32 {
+        1 << self.row_bits
+    }
+
+    // Seat ids have to fit in a u32 and every letter has to say which part it belongs to
+    pub fn check(&self) -> Result<(), String> {
+        if self.row_bits == 0 {
+            return Err(String::from("a plane needs at least one row bit"))
+        }
+        if self.row_bits.checked_add(self.column_bits).is_none_or(|bits| bits > 31) {
+            return Err(format!("{} row bits and {} column bits do not fit in a seat id", self.row_bits, self.column_bits))
+        }
+        let (row_lower, row_upper) = self.row_letters;
+        let (column_lower, column_upper) = self.column_letters;
+        let letters = [row_lower, row_upper, column_lower, column_upper];
+        for (index, letter) in letters.iter().enumerate() {
+            if letters[index + 1..].contains(letter) {
+                return Err(format!("the letter '{}' is used twice", letter))
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_bits(bits: &str) -> Result<u32, String> {
+    bits.parse().map_err(|_| format!("invalid number of bits '{}'", bits))
+}
+
+pub fn parse_letters(letters: &str) -> Result<(char, char), String> {
+    let chars: Vec<char> = letters.chars().collect();
+    match chars.as_slice() {
+        [lower, upper] => Ok((*lower, *upper)),
+        _ => Err(format!("expected a lower and an upper letter, got '{}'", letters)),
+    }
+}
+
+pub fn parse_layout(input: &str) -> Result<Layout, String> {
+    let mut layout = Layout::default();
+    for (index, line) in input.split('\n').enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let result = match tokens.as_slice() {
+            ["row-bits", bits, letters] => parse_bits(bits)
+                .and_then(|bits| Ok((bits, parse_letters(letters)?)))
+                .map(|(bits, letters)| {
+                    layout.row_bits = bits;
+                    layout.row_letters = letters;
+                }),
+            ["column-bits", bits, letters] => parse_bits(bits)
+                .and_then(|bits| Ok((bits, parse_letters(letters)?)))
+                .map(|(bits, letters)| {
+                    layout.column_bits = bits;
+                    layout.column_letters = letters;
+                }),
+            _ => Err(String::from("expected 'row-bits|column-bits bits letters'")),
+        };
+        result.map_err(|error| format!("line {}: {}", index + 1, error))?;
+    }
+    layout.check()?;
+    Ok(layout)
+}
+
+#[test]
+fn test_parse_layout() {
+    assert_eq!(parse_layout(""), Ok(Layout::default()));
+    assert_eq!(parse_layout("# small plane\nrow-bits 4 ab\n\ncolumn-bits 2 xy"), Ok(Layout {
+        row_bits: 4,
+        column_bits: 2,
+        row_letters: ('a', 'b'),
+        column_letters: ('x', 'y')
+    }));
+    assert_eq!(parse_layout("row-bits 4 ab\nseats 2"), Err(String::from("line 2: expected 'row-bits|column-bits bits letters'")));
+    assert_eq!(parse_layout("row-bits four ab"), Err(String::from("line 1: invalid number of bits 'four'")));
+    assert_eq!(parse_layout("column-bits 2 xyz"), Err(String::from("line 1: expected a lower and an upper letter, got 'xyz'")));
+    assert_eq!(parse_layout("column-bits 2 FL"), Err(String::from("the letter 'F' is used twice")));
+    assert_eq!(parse_layout("row-bits 30 FB"), Err(String::from("30 row bits and 3 column bits do not fit in a seat id")));
+    assert_eq!(parse_layout("row-bits 4294967295 FB"), Err(String::from("4294967295 row bits and 3 column bits do not fit in a seat id")));
+}
@@ -1,7 +1,12 @@
+use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::io::Read;
-use std::convert::TryInto;
+use cabin::Cabin;
+use layout::Layout;
+
+mod cabin;
+mod layout;
 
 fn read_file(file_path: &str) -> std::io::Result<String> {
     let mut file = File::open(file_path)?;
@@ -10,123 +15,229 @@ fn read_file(file_path: &str) -> std::io::Result<String> {
     Ok(string)
 }
 
-fn split_input(string: &String) -> Vec<&str> {
+fn split_input(string: &str) -> Vec<&str> {
     let strings: Vec<&str> = string
-        .split("\n")
+        .split('\n')
         .collect();
     strings
 }
 
-fn calculate_seat_id(row: u32, column: u32) -> u32 {
-    const ROW_MULTIPLIER: u32 = 8;
-    row * ROW_MULTIPLIER + column
+fn calculate_seat_id(row: u32, column: u32, columns: u32) -> u32 {
+    row * columns + column
 }
 
 #[test]
 fn test_calculate_seat_id() {
-    assert_eq!(calculate_seat_id(0, 1), 1);
-    assert_eq!(calculate_seat_id(1, 1), 9);
-    assert_eq!(calculate_seat_id(2, 1), 17);
-    assert_eq!(calculate_seat_id(1, 0), 8);
-    assert_eq!(calculate_seat_id(1, 2), 10);
+    assert_eq!(calculate_seat_id(0, 1, 8), 1);
+    assert_eq!(calculate_seat_id(1, 1, 8), 9);
+    assert_eq!(calculate_seat_id(2, 1, 8), 17);
+    assert_eq!(calculate_seat_id(1, 0, 8), 8);
+    assert_eq!(calculate_seat_id(1, 2, 8), 10);
+    assert_eq!(calculate_seat_id(2, 1, 4), 9);
 }
 
-fn find_row_and_column_strings(string: &str) -> (&str, &str) {
+// The row part ends at the first column letter
+fn find_row_and_column_strings(string: &str, column_letters: (char, char)) -> (&str, &str) {
     if string.is_empty() {
         ("", "")
-    } else {
-        if let Some(index) = string.find(|c: char| c == 'R' || c == 'L') {
-            if index == 0 {
-                ("", string)
-            } else {
-                string.split_at(index)
-            }
+    } else if let Some(index) = string.find([column_letters.0, column_letters.1]) {
+        if index == 0 {
+            ("", string)
         } else {
-            (string, "")
+            string.split_at(index)
         }
+    } else {
+        (string, "")
     }
 }
 
 #[test]
 fn test_find_row_and_column_strings() {
-    assert_eq!(find_row_and_column_strings(""), ("", ""));
-    assert_eq!(find_row_and_column_strings("F"), ("F", ""));
-    assert_eq!(find_row_and_column_strings("B"), ("B", ""));
-    assert_eq!(find_row_and_column_strings("R"), ("", "R"));
-    assert_eq!(find_row_and_column_strings("L"), ("", "L"));
-    assert_eq!(find_row_and_column_strings("FR"), ("F", "R"));
-    assert_eq!(find_row_and_column_strings("BR"), ("B", "R"));
-    assert_eq!(find_row_and_column_strings("FL"), ("F", "L"));
-    assert_eq!(find_row_and_column_strings("BL"), ("B", "L"));
-    assert_eq!(find_row_and_column_strings("BFFFBBFRRR"), ("BFFFBBF", "RRR"));
-    assert_eq!(find_row_and_column_strings("FFFBBBFRRR"), ("FFFBBBF", "RRR"));
-    assert_eq!(find_row_and_column_strings("BBFFBBFRLL"), ("BBFFBBF", "RLL"));
-}
-
-fn get_max_value(string: &str) -> u32 {
-    let power: u32 = string.len().try_into().unwrap();
-    const BASE: u32 = 2;
-    BASE.pow(power) - 1
+    let letters = ('L', 'R');
+    assert_eq!(find_row_and_column_strings("", letters), ("", ""));
+    assert_eq!(find_row_and_column_strings("F", letters), ("F", ""));
+    assert_eq!(find_row_and_column_strings("B", letters), ("B", ""));
+    assert_eq!(find_row_and_column_strings("R", letters), ("", "R"));
+    assert_eq!(find_row_and_column_strings("L", letters), ("", "L"));
+    assert_eq!(find_row_and_column_strings("FR", letters), ("F", "R"));
+    assert_eq!(find_row_and_column_strings("BR", letters), ("B", "R"));
+    assert_eq!(find_row_and_column_strings("FL", letters), ("F", "L"));
+    assert_eq!(find_row_and_column_strings("BL", letters), ("B", "L"));
+    assert_eq!(find_row_and_column_strings("BFFFBBFRRR", letters), ("BFFFBBF", "RRR"));
+    assert_eq!(find_row_and_column_strings("FFFBBBFRRR", letters), ("FFFBBBF", "RRR"));
+    assert_eq!(find_row_and_column_strings("BBFFBBFRLL", letters), ("BBFFBBF", "RLL"));
+    assert_eq!(find_row_and_column_strings("abyx", ('x', 'y')), ("ab", "yx"));
+}
+
+// Each character is one bit, most significant first: the lower letter keeps the lower
+// half (0), anything else takes the upper half (1)
+fn decode_bits(string: &str, lower: char) -> u32 {
+    string
+        .chars()
+        .fold(0, |value, character| value << 1 | (character != lower) as u32)
 }
 
 #[test]
-fn test_get_max_value() {
-    assert_eq!(get_max_value(""), 0);
-    assert_eq!(get_max_value("F"), 1);
-    assert_eq!(get_max_value("FF"), 3);
-    assert_eq!(get_max_value("FFFFFFF"), 127);
+fn test_decode_bits() {
+    assert_eq!(decode_bits("", 'F'), 0);
+    assert_eq!(decode_bits("F", 'F'), 0);
+    assert_eq!(decode_bits("B", 'F'), 1);
+    assert_eq!(decode_bits("L", 'L'), 0);
+    assert_eq!(decode_bits("R", 'L'), 1);
+    assert_eq!(decode_bits("FF", 'F'), 0);
+    assert_eq!(decode_bits("BB", 'F'), 3);
+    assert_eq!(decode_bits("LL", 'L'), 0);
+    assert_eq!(decode_bits("RR", 'L'), 3);
+    assert_eq!(decode_bits("FB", 'F'), 1);
+    assert_eq!(decode_bits("BF", 'F'), 2);
+    assert_eq!(decode_bits("LR", 'L'), 1);
+    assert_eq!(decode_bits("RL", 'L'), 2);
+    assert_eq!(decode_bits("BFFFBBF", 'F'), 70);
+    assert_eq!(decode_bits("FFFBBBF", 'F'), 14);
+    assert_eq!(decode_bits("BBFFBBF", 'F'), 102);
 }
 
-fn find_column_or_row(string: &str, lower_bound: u32) -> u32 {
-    if string.is_empty() {
-        0
-    } else {
-        let max_value = get_max_value(string) + lower_bound;
-        if string.len() == 1 {
-            if string == "F" || string == "L" {
-                return lower_bound
-            } else {
-                return max_value
-            }
-        } else {
-            let (first, new_string) = string.split_at(1);
-            let lower_bound = if first == "F" || first == "L" {
-                lower_bound
-            } else {
-                (max_value - lower_bound) / 2 + 1 + lower_bound
-            };
-            find_column_or_row(new_string, lower_bound)
+// Positions in errors are 1-based and count from the start of the whole pass
+fn check_part(part: &str, offset: usize, name: &str, bits: u32, (lower, upper): (char, char)) -> Result<(), String> {
+    if let Some((index, character)) = part.chars().enumerate().find(|&(_, character)| character != lower && character != upper) {
+        return Err(format!("'{}' at position {} is not {} or {}", character, offset + index + 1, lower, upper))
+    }
+    let length = part.chars().count();
+    if length != bits as usize {
+        return Err(format!("expected {} {} letters, got {}", bits, name, length))
+    }
+    Ok(())
+}
+
+fn encode_bits(value: u32, bits: u32, (lower, upper): (char, char)) -> String {
+    (0..bits)
+        .rev()
+        .map(|bit| if value >> bit & 1 == 1 { upper } else { lower })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct BoardingPass {
+    row: u32,
+    column: u32
+}
+
+impl BoardingPass {
+    // Rejects anything but exactly the layout's row letters followed by its column letters
+    fn decode(string: &str, layout: &Layout) -> Result<BoardingPass, String> {
+        if string.is_empty() {
+            return Err(String::from("empty boarding pass"))
         }
+        let (row, column) = find_row_and_column_strings(string, layout.column_letters);
+        check_part(row, 0, "row", layout.row_bits, layout.row_letters)?;
+        check_part(column, row.chars().count(), "column", layout.column_bits, layout.column_letters)?;
+        Ok(BoardingPass { row: decode_bits(row, layout.row_letters.0), column: decode_bits(column, layout.column_letters.0) })
+    }
+
+    // None when the row or column does not fit in the plane
+    fn encode(row: u32, column: u32, layout: &Layout) -> Option<String> {
+        if row >= layout.rows() || column >= layout.columns() {
+            return None
+        }
+        Some(encode_bits(row, layout.row_bits, layout.row_letters) + &encode_bits(column, layout.column_bits, layout.column_letters))
+    }
+
+    fn from_seat_id(seat_id: u32, layout: &Layout) -> Option<BoardingPass> {
+        let pass = BoardingPass { row: seat_id / layout.columns(), column: seat_id % layout.columns() };
+        if pass.row < layout.rows() { Some(pass) } else { None }
+    }
+
+    fn seat_id(&self, layout: &Layout) -> u32 {
+        calculate_seat_id(self.row, self.column, layout.columns())
     }
 }
 
 #[test]
-fn test_find_column_or_row() {
-    assert_eq!(find_column_or_row("", 0), 0);
-    assert_eq!(find_column_or_row("F", 0), 0);
-    assert_eq!(find_column_or_row("B", 0), 1);
-    assert_eq!(find_column_or_row("L", 0), 0);
-    assert_eq!(find_column_or_row("R", 0), 1);
-    assert_eq!(find_column_or_row("FF", 0), 0);
-    assert_eq!(find_column_or_row("BB", 0), 3);
-    assert_eq!(find_column_or_row("LL", 0), 0);
-    assert_eq!(find_column_or_row("RR", 0), 3);
-    assert_eq!(find_column_or_row("FB", 0), 1);
-    assert_eq!(find_column_or_row("BF", 0), 2);
-    assert_eq!(find_column_or_row("LR", 0), 1);
-    assert_eq!(find_column_or_row("RL", 0), 2);
-    assert_eq!(find_column_or_row("BFFFBBF", 0), 70);
-    assert_eq!(find_column_or_row("FFFBBBF", 0), 14);
-    assert_eq!(find_column_or_row("BBFFBBF", 0), 102);
-}
-
-fn find_highest_seat_id(input: &Vec<&str>) -> u32 {
+fn test_boarding_pass() {
+    let layout = Layout::default();
+    assert_eq!(BoardingPass::decode("BFFFBBFRRR", &layout), Ok(BoardingPass { row: 70, column: 7 }));
+    assert_eq!(BoardingPass::decode("BBFFBBFRLL", &layout).unwrap().seat_id(&layout), 820);
+    assert_eq!(BoardingPass::encode(70, 7, &layout), Some(String::from("BFFFBBFRRR")));
+    assert_eq!(BoardingPass::encode(128, 0, &layout), None);
+    assert_eq!(BoardingPass::encode(0, 8, &layout), None);
+    assert_eq!(BoardingPass::from_seat_id(820, &layout), Some(BoardingPass { row: 102, column: 4 }));
+    assert_eq!(BoardingPass::from_seat_id(1024, &layout), None);
+}
+
+#[test]
+fn test_boarding_pass_round_trip() {
+    // Every seat in the plane survives encoding and decoding, whatever the layout
+    let layouts = [
+        Layout::default(),
+        Layout { row_bits: 4, column_bits: 2, row_letters: ('a', 'b'), column_letters: ('x', 'y') },
+        Layout { row_bits: 3, column_bits: 0, ..Layout::default() },
+    ];
+    for layout in &layouts {
+        let seats = layout.rows() * layout.columns();
+        for seat_id in 0..seats {
+            let pass = BoardingPass::from_seat_id(seat_id, layout).unwrap();
+            let encoded = BoardingPass::encode(pass.row, pass.column, layout).unwrap();
+            assert_eq!(encoded.chars().count() as u32, layout.row_bits + layout.column_bits);
+            assert_eq!(BoardingPass::decode(&encoded, layout), Ok(pass));
+            assert_eq!(BoardingPass::decode(&encoded, layout).unwrap().seat_id(layout), seat_id);
+        }
+        assert_eq!(BoardingPass::from_seat_id(seats, layout), None);
+    }
+}
+
+// Decodes every non-blank line into a boarding pass with its line number. Passes that
+// cannot be decoded and passes for a seat that is already taken are left out and
+// reported as "line N: reason".
+fn decode_passes(input: &[&str], layout: &Layout) -> (Vec<(usize, BoardingPass)>, Vec<String>) {
+    let mut passes: Vec<(usize, BoardingPass)> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+    // Line of the pass that took each seat
+    let mut taken: HashMap<u32, usize> = HashMap::new();
+    for (index, line) in input.iter().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            continue
+        }
+        match BoardingPass::decode(line, layout) {
+            Ok(pass) => {
+                let seat_id = pass.seat_id(layout);
+                match taken.get(&seat_id) {
+                    Some(other_line) => errors.push(format!("line {}: seat {} is already taken by the pass on line {}", line_number, seat_id, other_line)),
+                    None => {
+                        taken.insert(seat_id, line_number);
+                        passes.push((line_number, pass));
+                    },
+                }
+            },
+            Err(error) => errors.push(format!("line {}: {}", line_number, error)),
+        }
+    }
+    (passes, errors)
+}
+
+#[test]
+fn test_decode_passes() {
+    let layout = Layout::default();
+    let input = ["BFFFBBFRRR", "", "BFFFBBFRR", "BFFXBBFRRR", "BFFFBBFRRR\r", "BFFFBBFRRRL", "FFFBBBFRRR", "\r", "   "];
+    let (passes, errors) = decode_passes(&input, &layout);
+    assert_eq!(passes, vec![(1, BoardingPass { row: 70, column: 7 }), (7, BoardingPass { row: 14, column: 7 })]);
+    assert_eq!(errors, vec![
+        "line 3: expected 3 column letters, got 2",
+        "line 4: 'X' at position 4 is not F or B",
+        "line 5: seat 567 is already taken by the pass on line 1",
+        "line 6: expected 3 column letters, got 4",
+        "line 9: ' ' at position 1 is not F or B",
+    ]);
+
+    assert_eq!(BoardingPass::decode("", &layout), Err(String::from("empty boarding pass")));
+    assert_eq!(BoardingPass::decode("BFFFBBRRR", &layout), Err(String::from("expected 7 row letters, got 6")));
+    assert_eq!(BoardingPass::decode("BFFFBBFRRF", &layout), Err(String::from("'F' at position 10 is not L or R")));
+}
+
+fn find_highest_seat_id(seat_ids: &[u32]) -> u32 {
     let mut highest_seat_id: u32 = 0;
-    for entry in input {
-        let (row, column) = find_row_and_column_strings(entry);
-        let row = find_column_or_row(row, 0);
-        let column = find_column_or_row(column, 0);
-        let seat_id = calculate_seat_id(row, column);
+    for &seat_id in seat_ids {
         if seat_id > highest_seat_id {
             highest_seat_id = seat_id;
         }
@@ -135,47 +246,115 @@ fn find_highest_seat_id(input: &Vec<&str>) -> u32 {
     highest_seat_id
 }
 
-fn find_missing_seat_id(input: &Vec<&str>) -> u32 {
-    let mut seat_ids: Vec<u32> = input
-        .iter()
-        .map(|entry| {
-            let (row, column) = find_row_and_column_strings(entry);
-            let row = find_column_or_row(row, 0);
-            let column = find_column_or_row(column, 0);
-            let seat_id = calculate_seat_id(row, column);
-            seat_id
-        })
-        .collect();
-    seat_ids.sort();
+// Every run of seat ids that nobody holds between the lowest and the highest taken seat,
+// as first and last id. Seats missing at the very front or back of the plane do not count.
+fn find_missing_seat_ids(seat_ids: &[u32]) -> Vec<(u32, u32)> {
+    let mut seat_ids = seat_ids.to_vec();
+    seat_ids.sort_unstable();
+    seat_ids.dedup();
 
-    let mut result: u32 = 0;
-    for index in 1..seat_ids.len()-1 {
-        let previous = seat_ids[index-1];
-        let current = seat_ids[index];
-        let next = seat_ids[index+1];
+    seat_ids
+        .windows(2)
+        .filter(|pair| pair[1] - pair[0] > 1)
+        .map(|pair| (pair[0] + 1, pair[1] - 1))
+        .collect()
+}
 
-        if previous + 1 == current && next - 1 == current {
-            continue
-        } else {
-            let a = current - previous - 1;
-            let b = next - current - 1;
-            result = if a > b {current - a} else {current + b};
-            break
-        }
+#[test]
+fn test_find_missing_seat_ids() {
+    assert_eq!(find_missing_seat_ids(&[]), vec![]);
+    assert_eq!(find_missing_seat_ids(&[5]), vec![]);
+    assert_eq!(find_missing_seat_ids(&[5, 6, 7]), vec![]);
+    assert_eq!(find_missing_seat_ids(&[7, 5]), vec![(6, 6)]);
+    assert_eq!(find_missing_seat_ids(&[3, 10, 4, 6, 6]), vec![(5, 5), (7, 9)]);
+}
+
+fn format_range((first, last): (u32, u32)) -> String {
+    if first == last {
+        first.to_string()
+    } else {
+        format!("{}-{}", first, last)
     }
+}
 
-    result
+fn parse_option<T>(value: Option<&String>, parse: impl Fn(&str) -> Result<T, String>) -> T {
+    let value = value.expect("Missing value for option");
+    parse(value).unwrap_or_else(|error| panic!("{}", error))
 }
 
 fn main() -> Result<(), std::io::Error> {
     let args: Vec<String> = env::args().collect();
     let path = &args[1];
 
+    let mut seats: Vec<u32> = Vec::new();
+    let mut map = false;
+    let mut layout = Layout::default();
+    let mut iter = args.iter().skip(2);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            // Replaces the whole layout, so only options after it change the file's values
+            "--layout" => {
+                let layout_path = iter.next().expect("--layout needs a file");
+                layout = layout::parse_layout(&read_file(layout_path)?)
+                    .unwrap_or_else(|error| panic!("Error in layout file: {}", error));
+            },
+            "--row-bits" => layout.row_bits = parse_option(iter.next(), layout::parse_bits),
+            "--column-bits" => layout.column_bits = parse_option(iter.next(), layout::parse_bits),
+            "--row-letters" => layout.row_letters = parse_option(iter.next(), layout::parse_letters),
+            "--column-letters" => layout.column_letters = parse_option(iter.next(), layout::parse_letters),
+            "--map" => map = true,
+            "--seat" => seats.push(iter.next().and_then(|id| id.parse().ok()).expect("--seat needs a seat id")),
+            _ => panic!("Unknown argument: {}", arg),
+        }
+    }
+
+    layout.check()
+        .unwrap_or_else(|error| panic!("Invalid layout: {}", error));
+
     let input = read_file(path)?;
     let input = split_input(&input);
 
-    println!("Highest seat id: {:?}", find_highest_seat_id(&input));
-    println!("Missing seat id: {:?}", find_missing_seat_id(&input));
+    let (passes, errors) = decode_passes(&input, &layout);
+    for error in errors {
+        eprintln!("{}", error);
+    }
+    let seat_ids: Vec<u32> = passes
+        .iter()
+        .map(|(_, pass)| pass.seat_id(&layout))
+        .collect();
+
+    println!("Highest seat id: {:?}", find_highest_seat_id(&seat_ids));
+    let missing: Vec<String> = find_missing_seat_ids(&seat_ids)
+        .into_iter()
+        .map(format_range)
+        .collect();
+    println!("Missing seat ids: {}", missing.join(", "));
+
+    if map {
+        let cabin = Cabin::new(&seat_ids, &layout);
+        print!("{}", cabin.render());
+        let free_seats: Vec<String> = cabin
+            .free_seats()
+            .iter()
+            .map(|seat_id| seat_id.to_string())
+            .collect();
+        println!("Free seats ({}): {}", free_seats.len(), free_seats.join(", "));
+        for block in cabin.free_blocks() {
+            println!(
+                "Free block: row {}, columns {} ({} seats)",
+                block.row,
+                format_range((block.first_column, block.last_column)),
+                block.last_column - block.first_column + 1
+            );
+        }
+    }
+
+    for seat_id in seats {
+        match BoardingPass::from_seat_id(seat_id, &layout).and_then(|pass| BoardingPass::encode(pass.row, pass.column, &layout)) {
+            Some(encoded) => println!("Seat {}: {}", seat_id, encoded),
+            None => println!("Seat {}: not in the plane", seat_id),
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,147 @@
+// The questions a form can ask. An alphabet is declared as a comma separated list of
+// character ranges and question ids, e.g. "a-z", "a-z,A-Z" or "q1,q2,q10". When every
+// question is a single character people write their answers as one word, otherwise
+// answers are question ids separated by spaces.
+
+use std::collections::HashMap;
+
+// One bit per question, in the order the alphabet declares them
+pub type Mask = u128;
+
+pub const MAX_QUESTIONS: usize = Mask::BITS as usize;
+
+#[derive(Debug, PartialEq)]
+pub struct Alphabet {
+    questions: Vec<String>,
+    // Index of each question, and of its character when every question is one character
+    indices: HashMap<String, usize>,
+    char_indices: HashMap<char, usize>,
+    separated: bool
+}
+
+impl Default for Alphabet {
+    fn default() -> Alphabet {
+        parse_alphabet("a-z").unwrap()
+    }
+}
+
+fn single_char(string: &str) -> Option<char> {
+    let mut chars = string.chars();
+    match (chars.next(), chars.next()) {
+        (Some(character), None) => Some(character),
+        _ => None,
+    }
+}
+
+pub fn parse_alphabet(spec: &str) -> Result<Alphabet, String> {
+    let mut questions: Vec<String> = Vec::new();
+    let mut indices: HashMap<String, usize> = HashMap::new();
+    for item in spec.split(',').map(|item| item.trim()) {
+        if item.is_empty() {
+            return Err(format!("empty question in alphabet '{}'", spec))
+        }
+        if item.contains(char::is_whitespace) {
+            return Err(format!("question '{}' contains whitespace", item))
+        }
+        let range = item
+            .split_once('-')
+            .and_then(|(first, last)| Some((single_char(first)?, single_char(last)?)));
+        let items: Vec<String> = match range {
+            Some((first, last)) if first > last => return Err(format!("range '{}' is reversed", item)),
+            Some((first, last)) => (first..=last).map(|character| character.to_string()).collect(),
+            None => vec![item.to_string()],
+        };
+        for question in items {
+            if indices.contains_key(&question) {
+                return Err(format!("question '{}' is declared twice", question))
+            }
+            indices.insert(question.clone(), questions.len());
+            questions.push(question);
+        }
+    }
+    if questions.len() > MAX_QUESTIONS {
+        return Err(format!("alphabet has {} questions, at most {} are supported", questions.len(), MAX_QUESTIONS))
+    }
+    let separated = questions.iter().any(|question| single_char(question).is_none());
+    let char_indices = if separated {
+        HashMap::new()
+    } else {
+        questions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, question)| Some((single_char(question)?, index)))
+            .collect()
+    };
+    Ok(Alphabet { questions, indices, char_indices, separated })
+}
+
+impl Alphabet {
+    pub fn len(&self) -> usize {
+        self.questions.len()
+    }
+
+    pub fn question(&self, index: usize) -> &str {
+        &self.questions[index]
+    }
+
+
+    // The answers of one person. A line ending in CRLF is fine, any other character
+    // that is not a question is rejected, as is a line of nothing but whitespace.
+    pub fn mask(&self, answers: &str) -> Result<Mask, String> {
+        let answers = answers.strip_suffix('\r').unwrap_or(answers);
+        let mut mask: Mask = 0;
+        if self.separated {
+            if answers.trim().is_empty() && !answers.is_empty() {
+                return Err(String::from("expected question ids, got only whitespace"))
+            }
+            for question in answers.split_whitespace() {
+                let index = self.indices.get(question).ok_or_else(|| format!("unknown question '{}'", question))?;
+                mask |= 1 << index;
+            }
+        } else {
+            for (index, character) in answers.chars().enumerate() {
+                let bit = self.char_indices.get(&character).ok_or_else(|| {
+                    format!("unexpected character '{}' at column {}", character.escape_debug(), index + 1)
+                })?;
+                mask |= 1 << bit;
+            }
+        }
+        Ok(mask)
+    }
+}
+
+#[test]
+fn test_parse_alphabet() {
+    let alphabet = Alphabet::default();
+    assert_eq!(alphabet.len(), 26);
+    assert_eq!(alphabet.question(25), "z");
+    assert!(!alphabet.separated);
+
+    assert_eq!(parse_alphabet("a-c,A-C,-").unwrap().questions, vec!["a", "b", "c", "A", "B", "C", "-"]);
+    let alphabet = parse_alphabet("q1, q2,smoker,a-b").unwrap();
+    assert_eq!(alphabet.questions, vec!["q1", "q2", "smoker", "a", "b"]);
+    assert!(alphabet.separated);
+
+    assert_eq!(parse_alphabet("z-a"), Err(String::from("range 'z-a' is reversed")));
+    assert_eq!(parse_alphabet("a-z,q"), Err(String::from("question 'q' is declared twice")));
+    assert_eq!(parse_alphabet("a,,b"), Err(String::from("empty question in alphabet 'a,,b'")));
+    assert_eq!(parse_alphabet("\u{100}-\u{200}"), Err(String::from("alphabet has 257 questions, at most 128 are supported")));
+}
+
+#[test]
+fn test_mask() {
+    let alphabet = Alphabet::default();
+    assert_eq!(alphabet.mask(""), Ok(0));
+    assert_eq!(alphabet.mask("aba"), Ok(0b11));
+    assert_eq!(alphabet.mask("z\r"), Ok(1 << 25));
+    assert_eq!(alphabet.mask("ab c"), Err(String::from("unexpected character ' ' at column 3")));
+    assert_eq!(alphabet.mask("a\rb"), Err(String::from("unexpected character '\\r' at column 2")));
+
+    let alphabet = parse_alphabet("q1,q2,q10").unwrap();
+    assert_eq!(alphabet.mask("q10  q1"), Ok(0b101));
+    assert_eq!(alphabet.mask("q1 q11"), Err(String::from("unknown question 'q11'")));
+    assert_eq!(alphabet.mask(" \t"), Err(String::from("expected question ids, got only whitespace")));
+
+    let alphabet = parse_alphabet("\u{100}-\u{17f}").unwrap();
+    assert_eq!(alphabet.mask("\u{17f}\u{100}"), Ok(1 << 127 | 1));
+}
@@ -1,7 +1,13 @@
 use std::env;
 use std::fs::File;
+use std::fmt;
 use std::io::Read;
 
+use alphabet::{Alphabet, Mask};
+
+mod alphabet;
+mod report;
+
 fn read_file(file_path: &str) -> std::io::Result<String> {
     let mut file = File::open(file_path)?;
     let mut string = String::new();
@@ -9,43 +15,32 @@ fn read_file(file_path: &str) -> std::io::Result<String> {
     Ok(string)
 }
 
-fn split_input(string: &String) -> Vec<&str> {
-    let strings: Vec<&str> = string
-        .split("\n\n")
-        .collect();
-    strings
-}
-
-fn get_unique_answers(answers: &str) -> Vec<char> {
-    let mut answers: Vec<char> = answers
-        .chars()
-        .filter(|&character| character != '\n')
-        .collect();
-    answers.sort();
-    answers.dedup();
-    answers
-}
-
-#[test]
-fn test_count_unique_answers() {
-    assert_eq!(get_unique_answers(""), vec![]);
-    assert_eq!(get_unique_answers("a"), vec!['a']);
-    assert_eq!(get_unique_answers("aa"), vec!['a']);
-    assert_eq!(get_unique_answers("ab"), vec!['a', 'b']);
-    assert_eq!(get_unique_answers("aba"), vec!['a', 'b']);
-}
-
-fn sum_answers(input: &Vec<&str>) -> u32 {
-    let result: u32 = input
-        .iter()
-        .map(|group| get_unique_answers(group).len() as u32)
-        .sum();
-    result
+// Groups end at a blank line, which may itself end in CRLF
+fn split_input(string: &str) -> Vec<&str> {
+    let mut groups: Vec<&str> = Vec::new();
+    let mut rest = string;
+    loop {
+        let separator = rest.match_indices('\n').find_map(|(index, _)| {
+            let after = &rest[index + 1..];
+            let blank = after.strip_prefix('\r').unwrap_or(after);
+            blank.strip_prefix('\n').map(|next| (index, rest.len() - next.len()))
+        });
+        match separator {
+            Some((end, next)) => {
+                groups.push(&rest[..end]);
+                rest = &rest[next..];
+            },
+            None => {
+                groups.push(rest);
+                return groups
+            },
+        }
+    }
 }
 
 fn split_group(group: &str) -> Vec<&str> {
     group
-        .split("\n")
+        .split('\n')
         .collect::<Vec<&str>>()
 }
 
@@ -57,51 +52,224 @@ fn test_split_group() {
     assert_eq!(split_group("ab\na"), vec!["ab", "a"]);
 }
 
-fn count_answers(groups: &Vec<&str>) -> u32 {
-    let mut result: u32 = 0;
-    for group in groups {
-        let unique_answers = get_unique_answers(group);
-        let group_answers = split_group(group);
-        let group_len = group_answers.len();
-
-        for unique_answer in unique_answers {
-            let count = group_answers
-                .iter()
-                .map(|group_answer| group_answer.contains(unique_answer))
-                .filter(|&answer| answer == true)
-                .count();
-            if count == group_len {
-                result += 1;
-            }
+// One mask per person, empty lines are not people. Errors name the line in the whole
+// input, given the line the group starts on.
+fn parse_group(group: &str, first_line: usize, alphabet: &Alphabet) -> Result<Vec<Mask>, Vec<String>> {
+    let mut masks: Vec<Mask> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+    for (index, person) in split_group(group).into_iter().enumerate() {
+        if person.strip_suffix('\r').unwrap_or(person).is_empty() {
+            continue
+        }
+        match alphabet.mask(person) {
+            Ok(mask) => masks.push(mask),
+            Err(error) => errors.push(format!("line {}: {}", first_line + index, error)),
+        }
+    }
+    if errors.is_empty() { Ok(masks) } else { Err(errors) }
+}
+
+fn parse_groups(input: &[&str], alphabet: &Alphabet) -> Result<Vec<Vec<Mask>>, Vec<String>> {
+    let mut groups: Vec<Vec<Mask>> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+    // Groups are separated by exactly one blank line, extra blank lines stay in a group
+    let mut first_line = 1;
+    for group in input {
+        match parse_group(group, first_line, alphabet) {
+            Ok(masks) => groups.push(masks),
+            Err(group_errors) => errors.extend(group_errors),
+        }
+        first_line += split_group(group).len() + 1;
+    }
+    if errors.is_empty() { Ok(groups) } else { Err(errors) }
+}
+
+#[test]
+fn test_parse_groups() {
+    let alphabet = Alphabet::default();
+    let input = split_input("ab\nc\n\nd\n\n\nE\nf g\n\nh");
+    assert_eq!(parse_groups(&input, &alphabet), Err(vec![
+        String::from("line 7: unexpected character 'E' at column 1"),
+        String::from("line 8: unexpected character ' ' at column 2"),
+    ]));
+    let input = split_input("ab\nc\n\nd\n\n\ne\r\nf\n");
+    assert_eq!(parse_groups(&input, &alphabet), Ok(vec![vec![0b11, 0b100], vec![0b1000], vec![0b10000, 0b100000]]));
+    assert_eq!(split_input("a\n\n\n\nb"), vec!["a", "", "b"]);
+    let input = split_input("ab\n \nc\n\n\t\r\n\r\nd");
+    assert_eq!(parse_groups(&input, &alphabet), Err(vec![
+        String::from("line 2: unexpected character ' ' at column 1"),
+        String::from("line 5: unexpected character '\\t' at column 1"),
+    ]));
+}
+
+#[test]
+fn test_parse_crlf_file() {
+    let alphabet = Alphabet::default();
+    let input = split_input("ab\r\nc\r\n\r\nd\r\n");
+    assert_eq!(input, vec!["ab\r\nc\r", "d\r\n"]);
+    let groups = parse_groups(&input, &alphabet).unwrap();
+    assert_eq!(groups, vec![vec![0b11, 0b100], vec![0b1000]]);
+    assert_eq!(sum_answers(&groups), 4);
+    assert_eq!(count_answers(&groups), 1);
+
+    let input = split_input("ab\r\nc\r\n\r\n\r\nd\r\nE\r\n");
+    assert_eq!(parse_groups(&input, &alphabet), Err(vec![String::from("line 6: unexpected character 'E' at column 1")]));
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Aggregation {
+    Union,
+    Intersection,
+    AtLeast(usize),
+    ExactlyOne,
+}
+
+impl fmt::Display for Aggregation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Aggregation::Union => write!(f, "any"),
+            Aggregation::Intersection => write!(f, "all"),
+            Aggregation::AtLeast(count) => write!(f, "at-least={}", count),
+            Aggregation::ExactlyOne => write!(f, "exactly-one"),
+        }
+    }
+}
+
+impl Aggregation {
+    fn parse(string: &str) -> Result<Aggregation, String> {
+        match string {
+            "any" => Ok(Aggregation::Union),
+            "all" => Ok(Aggregation::Intersection),
+            "exactly-one" => Ok(Aggregation::ExactlyOne),
+            _ => match string.strip_prefix("at-least=") {
+                Some(count) => count
+                    .parse()
+                    .map(Aggregation::AtLeast)
+                    .map_err(|_| format!("invalid count '{}'", count)),
+                None => Err(format!("unknown aggregation '{}', expected any, all, at-least=K or exactly-one", string)),
+            },
+        }
+    }
+
+    // The questions of a group selected by the aggregation, as a mask.
+    // A group without people has answered nothing.
+    fn apply(&self, masks: &[Mask]) -> Mask {
+        match self {
+            Aggregation::Union => masks.iter().fold(0, |union, mask| union | mask),
+            Aggregation::Intersection if masks.is_empty() => 0,
+            Aggregation::Intersection => masks.iter().fold(!0, |intersection, mask| intersection & mask),
+            Aggregation::AtLeast(count) => (0..alphabet::MAX_QUESTIONS)
+                .filter(|bit| *count > 0 && masks.iter().filter(|&&mask| mask >> bit & 1 == 1).count() >= *count)
+                .fold(0, |result, bit| result | 1 << bit),
+            Aggregation::ExactlyOne => {
+                let (once, twice) = masks
+                    .iter()
+                    .fold((0, 0), |(once, twice), mask| (once | mask, twice | once & mask));
+                once & !twice
+            },
         }
     }
 
-    result
+    fn total(&self, groups: &[Vec<Mask>]) -> u32 {
+        groups
+            .iter()
+            .map(|masks| self.apply(masks).count_ones())
+            .sum()
+    }
+}
+
+#[test]
+fn test_aggregations() {
+    let alphabet = Alphabet::default();
+    let answer_mask = |answers: &str| alphabet.mask(answers).unwrap();
+    let masks = [answer_mask("abc"), answer_mask("ab"), answer_mask("bd")];
+    assert_eq!(Aggregation::Union.apply(&masks), answer_mask("abcd"));
+    assert_eq!(Aggregation::Intersection.apply(&masks), answer_mask("b"));
+    assert_eq!(Aggregation::AtLeast(2).apply(&masks), answer_mask("ab"));
+    assert_eq!(Aggregation::AtLeast(0).apply(&masks), 0);
+    assert_eq!(Aggregation::AtLeast(4).apply(&masks), 0);
+    assert_eq!(Aggregation::ExactlyOne.apply(&masks), answer_mask("cd"));
+    assert_eq!(Aggregation::Intersection.apply(&[]), 0);
+
+    assert_eq!(Aggregation::parse("at-least=2"), Ok(Aggregation::AtLeast(2)));
+    assert_eq!(Aggregation::parse("at-least=two"), Err(String::from("invalid count 'two'")));
+    assert!(Aggregation::parse("some").is_err());
+    for aggregation in &[Aggregation::Union, Aggregation::Intersection, Aggregation::AtLeast(3), Aggregation::ExactlyOne] {
+        assert_eq!(Aggregation::parse(&aggregation.to_string()), Ok(*aggregation));
+    }
+}
+
+fn sum_answers(groups: &[Vec<Mask>]) -> u32 {
+    Aggregation::Union.total(groups)
+}
+
+fn count_answers(groups: &[Vec<Mask>]) -> u32 {
+    Aggregation::Intersection.total(groups)
 }
 
 #[test]
 fn test_count_answers() {
-    assert_eq!(count_answers(&vec![""]), 0);
-    assert_eq!(count_answers(&vec!["a"]), 1);
-    assert_eq!(count_answers(&vec!["aa"]), 1);
-    assert_eq!(count_answers(&vec!["ab"]), 2);
-    assert_eq!(count_answers(&vec!["a\nb"]), 0);
-    assert_eq!(count_answers(&vec!["a\na"]), 1);
-    assert_eq!(count_answers(&vec!["ab\na"]), 1);
-    assert_eq!(count_answers(&vec!["a\nba"]), 1);
-    assert_eq!(count_answers(&vec!["a\na\na"]), 1);
+    let count_answers = |groups: &[&str]| count_answers(&parse_groups(groups, &Alphabet::default()).unwrap());
+    assert_eq!(count_answers(&[""]), 0);
+    assert_eq!(count_answers(&["a"]), 1);
+    assert_eq!(count_answers(&["aa"]), 1);
+    assert_eq!(count_answers(&["ab"]), 2);
+    assert_eq!(count_answers(&["a\nb"]), 0);
+    assert_eq!(count_answers(&["a\na"]), 1);
+    assert_eq!(count_answers(&["ab\na"]), 1);
+    assert_eq!(count_answers(&["a\nba"]), 1);
+    assert_eq!(count_answers(&["a\na\na"]), 1);
+    assert_eq!(count_answers(&["ab\na\n"]), 1);
+    assert_eq!(sum_answers(&parse_groups(&["ab\nac", "b"], &Alphabet::default()).unwrap()), 4);
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     let path = &args[1];
 
+    let mut queries: Vec<Aggregation> = Vec::new();
+    let mut report_format: Option<&str> = None;
+    let mut alphabet = Alphabet::default();
+    let mut iter = args.iter().skip(2);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--query" => {
+                let query = iter.next().expect("--query needs an aggregation");
+                queries.push(Aggregation::parse(query).unwrap_or_else(|error| panic!("{}", error)));
+            },
+            "--report" => report_format = Some(iter.next().expect("--report needs text or json").as_str()),
+            "--alphabet" => {
+                let spec = iter.next().expect("--alphabet needs a list of questions");
+                alphabet = alphabet::parse_alphabet(spec).unwrap_or_else(|error| panic!("Invalid alphabet: {}", error));
+            },
+            _ => panic!("Unknown argument: {}", arg),
+        }
+    }
+
     let input = read_file(path)
         .expect("Error reading file.");
     let input = split_input(&input);
-    let result = sum_answers(&input);
-    println!("Part 1: {:?}", result);
+    let groups = parse_groups(&input, &alphabet)
+        .unwrap_or_else(|errors| panic!("Invalid answers:\n{}", errors.join("\n")));
 
-    let result = count_answers(&input);
-    println!("Part 2: {:?}", result);
+    if queries.is_empty() {
+        let result = sum_answers(&groups);
+        println!("Part 1: {:?}", result);
+
+        let result = count_answers(&groups);
+        println!("Part 2: {:?}", result);
+    }
+
+    for query in queries {
+        println!("{}: {}", query, query.total(&groups));
+    }
+
+    if let Some(report_format) = report_format {
+        let report = report::build_report(&groups, &alphabet);
+        match report_format {
+            "text" => print!("{}", report::format_text(&report)),
+            "json" => print!("{}", report::format_json(&report)),
+            _ => panic!("Unknown report format: {}, expected text or json", report_format),
+        }
+    }
 }
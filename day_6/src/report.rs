@@ -0,0 +1,179 @@
+// Statistics over every group: how often each question was answered, how big the groups
+// are and which two groups answered the most alike. Groups are numbered from 1.
+use crate::alphabet::{Alphabet, Mask};
+use crate::Aggregation;
+
+#[derive(Debug, PartialEq)]
+pub struct Similarity {
+    pub groups: (usize, usize),
+    pub shared: u32,
+    pub combined: u32
+}
+
+impl Similarity {
+    // Jaccard index of the two groups' answer sets
+    fn jaccard(&self) -> f64 {
+        self.shared as f64 / self.combined as f64
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Report {
+    // For each question its name and the people and the groups that answered it
+    questions: Vec<String>,
+    people: Vec<u32>,
+    groups: Vec<u32>,
+    // Group size and how many groups have it, smallest first
+    sizes: Vec<(usize, u32)>,
+    most_similar: Option<Similarity>
+}
+
+fn count_bits(masks: impl Iterator<Item = Mask>, questions: usize) -> Vec<u32> {
+    let mut counts = vec![0; questions];
+    for mask in masks {
+        for (question, count) in counts.iter_mut().enumerate() {
+            *count += (mask >> question & 1) as u32;
+        }
+    }
+    counts
+}
+
+fn json_string(string: &str) -> String {
+    format!("\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Groups that answered nothing are not similar to anything. Ties go to the earliest pair.
+fn find_most_similar(unions: &[Mask]) -> Option<Similarity> {
+    let mut best: Option<Similarity> = None;
+    for (first, a) in unions.iter().enumerate() {
+        for (second, b) in unions.iter().enumerate().skip(first + 1) {
+            let combined = (a | b).count_ones();
+            if combined == 0 {
+                continue
+            }
+            let shared = (a & b).count_ones();
+            let better = match &best {
+                Some(best) => shared as u64 * best.combined as u64 > best.shared as u64 * combined as u64,
+                None => true,
+            };
+            if better {
+                best = Some(Similarity { groups: (first + 1, second + 1), shared, combined });
+            }
+        }
+    }
+    best
+}
+
+pub fn build_report(groups: &[Vec<Mask>], alphabet: &Alphabet) -> Report {
+    let unions: Vec<Mask> = groups
+        .iter()
+        .map(|masks| Aggregation::Union.apply(masks))
+        .collect();
+
+    let mut sizes: Vec<(usize, u32)> = Vec::new();
+    for masks in groups {
+        match sizes.iter_mut().find(|(size, _)| *size == masks.len()) {
+            Some((_, count)) => *count += 1,
+            None => sizes.push((masks.len(), 1)),
+        }
+    }
+    sizes.sort_unstable();
+
+    Report {
+        questions: (0..alphabet.len()).map(|index| alphabet.question(index).to_string()).collect(),
+        people: count_bits(groups.iter().flatten().copied(), alphabet.len()),
+        groups: count_bits(unions.iter().copied(), alphabet.len()),
+        sizes,
+        most_similar: find_most_similar(&unions),
+    }
+}
+
+pub fn format_text(report: &Report) -> String {
+    let mut output = String::from("Questions:\n");
+    for (index, question) in report.questions.iter().enumerate() {
+        output.push_str(&format!("{}: {} people in {} groups\n", question, report.people[index], report.groups[index]));
+    }
+    output.push_str("Group sizes:\n");
+    for (size, count) in &report.sizes {
+        output.push_str(&format!("{} people: {} groups\n", size, count));
+    }
+    match &report.most_similar {
+        Some(similarity) => output.push_str(&format!(
+            "Most similar groups: {} and {} (jaccard {:.3}, {} of {} questions shared)\n",
+            similarity.groups.0,
+            similarity.groups.1,
+            similarity.jaccard(),
+            similarity.shared,
+            similarity.combined
+        )),
+        None => output.push_str("Most similar groups: none\n"),
+    }
+    output
+}
+
+pub fn format_json(report: &Report) -> String {
+    let questions: Vec<String> = report.questions
+        .iter()
+        .enumerate()
+        .map(|(index, question)| {
+            format!("{}:{{\"people\":{},\"groups\":{}}}", json_string(question), report.people[index], report.groups[index])
+        })
+        .collect();
+    let sizes: Vec<String> = report.sizes
+        .iter()
+        .map(|(size, count)| format!("\"{}\":{}", size, count))
+        .collect();
+    let most_similar = match &report.most_similar {
+        Some(similarity) => format!(
+            "{{\"groups\":[{},{}],\"jaccard\":{},\"shared\":{},\"combined\":{}}}",
+            similarity.groups.0,
+            similarity.groups.1,
+            similarity.jaccard(),
+            similarity.shared,
+            similarity.combined
+        ),
+        None => String::from("null"),
+    };
+    format!(
+        "{{\"questions\":{{{}}},\"group_sizes\":{{{}}},\"most_similar\":{}}}\n",
+        questions.join(","),
+        sizes.join(","),
+        most_similar
+    )
+}
+
+#[test]
+fn test_build_report() {
+    let alphabet = Alphabet::default();
+    let groups = crate::parse_groups(&["abc", "a\nb\nc", "ab\nac", "a\na\na\na", "b", "", "ab"], &alphabet).unwrap();
+    let report = build_report(&groups, &alphabet);
+    assert_eq!(&report.people[..4], &[9, 5, 3, 0]);
+    assert_eq!(&report.groups[..4], &[5, 5, 3, 0]);
+    assert_eq!(report.sizes, vec![(0, 1), (1, 3), (2, 1), (3, 1), (4, 1)]);
+    assert_eq!(report.most_similar, Some(Similarity { groups: (1, 2), shared: 3, combined: 3 }));
+
+    let report = build_report(&[vec![], vec![]], &alphabet);
+    assert_eq!(report.most_similar, None);
+}
+
+#[test]
+fn test_format_report() {
+    let groups = vec![vec![0b11], vec![0b1, 0b10, 0b100]];
+    let report = build_report(&groups, &Alphabet::default());
+    let text = format_text(&report);
+    assert!(text.starts_with("Questions:\na: 2 people in 2 groups\nb: 2 people in 2 groups\nc: 1 people in 1 groups\nd: 0 people in 0 groups\n"));
+    assert!(text.ends_with("Group sizes:\n1 people: 1 groups\n3 people: 1 groups\nMost similar groups: 1 and 2 (jaccard 0.667, 2 of 3 questions shared)\n"));
+
+    let json = format_json(&report);
+    assert!(json.starts_with("{\"questions\":{\"a\":{\"people\":2,\"groups\":2},\"b\":"));
+    assert!(json.ends_with(",\"z\":{\"people\":0,\"groups\":0}},\"group_sizes\":{\"1\":1,\"3\":1},\"most_similar\":{\"groups\":[1,2],\"jaccard\":0.6666666666666666,\"shared\":2,\"combined\":3}}\n"));
+}
+
+#[test]
+fn test_report_question_ids() {
+    let alphabet = crate::alphabet::parse_alphabet("q1,\"hi\"").unwrap();
+    let groups = crate::parse_groups(&["q1 \"hi\"\nq1"], &alphabet).unwrap();
+    let report = build_report(&groups, &alphabet);
+    assert!(format_text(&report).starts_with("Questions:\nq1: 2 people in 1 groups\n\"hi\": 1 people in 1 groups\n"));
+    assert!(format_json(&report).starts_with("{\"questions\":{\"q1\":{\"people\":2,\"groups\":1},\"\\\"hi\\\"\":{\"people\":1,\"groups\":1}}"));
+}
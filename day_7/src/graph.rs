@@ -0,0 +1,323 @@
+// The bag rules as a graph with an edge from every bag to each bag it directly contains.
+// Colours are numbered in the order they first appear, contents are kept in both
+// directions so either question is answered by visiting every rule at most once.
+// A graph is only built from valid rules: every colour has exactly one rule and no bag
+// ends up inside itself, so neither question can loop. Counting the bags inside can still
+// overflow, which is reported as an error.
+use std::collections::{HashMap, VecDeque};
+use crate::Rule;
+
+#[derive(Clone, Copy)]
+pub enum Selection<'s> {
+    All,
+    Inside(&'s str),
+    Containing(&'s str),
+}
+
+fn dot_id(colour: &str) -> String {
+    format!("\"{}\"", colour.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    New,
+    OnPath,
+    Done,
+}
+
+pub struct BagGraph<'a> {
+    colours: Vec<&'a str>,
+    indices: HashMap<&'a str, usize>,
+    // Line of the rule for each colour, None while only other rules mention it
+    rule_lines: Vec<Option<usize>>,
+    // Bags directly inside each bag, with their amounts
+    contents: Vec<Vec<(usize, u32)>>,
+    // Bags that directly contain each bag
+    containers: Vec<Vec<usize>>
+}
+
+impl<'a> BagGraph<'a> {
+    // Lists every duplicate rule, every colour without a rule and every cycle along with
+    // the line it was found on, in line order
+    pub fn new(rules: &[Rule<'a>]) -> Result<BagGraph<'a>, Vec<(usize, String)>> {
+        let mut graph = BagGraph {
+            colours: Vec::new(),
+            indices: HashMap::new(),
+            rule_lines: Vec::new(),
+            contents: Vec::new(),
+            containers: Vec::new()
+        };
+        let mut errors: Vec<(usize, String)> = Vec::new();
+        for rule in rules {
+            let outer = graph.add_colour(rule.outer_bag);
+            if let Some(line_number) = graph.rule_lines[outer] {
+                errors.push((rule.line_number, format!("'{}' already has a rule on line {}", rule.outer_bag, line_number)));
+                continue
+            }
+            graph.rule_lines[outer] = Some(rule.line_number);
+            for bag in &rule.inner_bags {
+                let inner = graph.add_colour(bag.bag_type);
+                graph.contents[outer].push((inner, bag.amount));
+                graph.containers[inner].push(outer);
+            }
+        }
+
+        for (bag, contents) in graph.contents.iter().enumerate() {
+            for &(inner, _) in contents {
+                if graph.rule_lines[inner].is_none() {
+                    let line_number = graph.rule_lines[bag].unwrap();
+                    errors.push((line_number, format!("'{}' has no rule of its own", graph.colours[inner])));
+                }
+            }
+        }
+
+        let mut states = vec![Visit::New; graph.colours.len()];
+        let mut path: Vec<usize> = Vec::new();
+        for bag in 0..graph.colours.len() {
+            graph.find_cycles(bag, &mut states, &mut path, &mut errors);
+        }
+
+        if errors.is_empty() {
+            Ok(graph)
+        } else {
+            errors.sort_by_key(|&(line_number, _)| line_number);
+            Err(errors)
+        }
+    }
+
+    // Depth first search that reports each edge leading back to a bag still on the path
+    fn find_cycles(&self, bag: usize, states: &mut Vec<Visit>, path: &mut Vec<usize>, errors: &mut Vec<(usize, String)>) {
+        if states[bag] != Visit::New {
+            return
+        }
+        states[bag] = Visit::OnPath;
+        path.push(bag);
+        for &(inner, _) in &self.contents[bag] {
+            if states[inner] == Visit::OnPath {
+                let start = path.iter().position(|&on_path| on_path == inner).unwrap();
+                let cycle: Vec<&str> = path[start..]
+                    .iter()
+                    .chain(std::iter::once(&inner))
+                    .map(|&colour| self.colours[colour])
+                    .collect();
+                errors.push((self.rule_lines[bag].unwrap(), format!("'{}' ends up inside itself: {}", self.colours[inner], cycle.join(" -> "))));
+            } else {
+                self.find_cycles(inner, states, path, errors);
+            }
+        }
+        path.pop();
+        states[bag] = Visit::Done;
+    }
+
+    fn add_colour(&mut self, colour: &'a str) -> usize {
+        if let Some(&index) = self.indices.get(colour) {
+            return index
+        }
+        self.colours.push(colour);
+        self.rule_lines.push(None);
+        self.contents.push(Vec::new());
+        self.containers.push(Vec::new());
+        self.indices.insert(colour, self.colours.len() - 1);
+        self.colours.len() - 1
+    }
+
+    // Breadth first search along the contents, or along the containers when reversed.
+    // The start itself is not included.
+    fn reachable(&self, start: usize, reverse: bool) -> Vec<usize> {
+        let mut seen = vec![false; self.colours.len()];
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        let mut found: Vec<usize> = Vec::new();
+        seen[start] = true;
+        queue.push_back(start);
+        while let Some(bag) = queue.pop_front() {
+            let mut visit = |other: usize| {
+                if !seen[other] {
+                    seen[other] = true;
+                    found.push(other);
+                    queue.push_back(other);
+                }
+            };
+            if reverse {
+                self.containers[bag].iter().for_each(|&outer| visit(outer));
+            } else {
+                self.contents[bag].iter().for_each(|&(inner, _)| visit(inner));
+            }
+        }
+        found
+    }
+
+    // Every bag that can hold the colour somewhere inside it, nearest first.
+    // None when the colour is not mentioned by any rule.
+    pub fn containers_of(&self, colour: &str) -> Option<Vec<&'a str>> {
+        let start = *self.indices.get(colour)?;
+        Some(self.reachable(start, true)
+            .into_iter()
+            .map(|bag| self.colours[bag])
+            .collect())
+    }
+
+    // The graph in Graphviz DOT, every edge labelled with its amount. A selection keeps
+    // only the chosen colour and the bags it holds or the bags that can hold it.
+    // None when the chosen colour is not mentioned by any rule.
+    pub fn to_dot(&self, selection: Selection) -> Option<String> {
+        let selected: Vec<usize> = match selection {
+            Selection::All => (0..self.colours.len()).collect(),
+            Selection::Inside(colour) | Selection::Containing(colour) => {
+                let start = *self.indices.get(colour)?;
+                let mut selected = vec![start];
+                selected.extend(self.reachable(start, matches!(selection, Selection::Containing(_))));
+                selected
+            },
+        };
+        let mut included = vec![false; self.colours.len()];
+        for &bag in &selected {
+            included[bag] = true;
+        }
+
+        let mut output = String::from("digraph bags {\n");
+        for &bag in &selected {
+            output.push_str(&format!("    {};\n", dot_id(self.colours[bag])));
+        }
+        for &bag in &selected {
+            for &(inner, amount) in self.contents[bag].iter().filter(|&&(inner, _)| included[inner]) {
+                output.push_str(&format!("    {} -> {} [label=\"{}\"];\n", dot_id(self.colours[bag]), dot_id(self.colours[inner]), amount));
+            }
+        }
+        output.push_str("}\n");
+        Some(output)
+    }
+
+    // How many bags a bag of the colour holds, not counting itself.
+    // None when the colour is not mentioned by any rule.
+    pub fn count_inside(&self, colour: &str) -> Option<Result<u64, String>> {
+        let start = *self.indices.get(colour)?;
+        let mut counts: Vec<Option<u64>> = vec![None; self.colours.len()];
+        Some(self.count_inside_index(start, &mut counts)
+            .ok_or_else(|| format!("a '{}' bag holds more than {} bags", colour, u64::MAX)))
+    }
+
+    // None when the count does not fit in a u64
+    fn count_inside_index(&self, bag: usize, counts: &mut Vec<Option<u64>>) -> Option<u64> {
+        if let Some(count) = counts[bag] {
+            return Some(count)
+        }
+        let mut count: u64 = 0;
+        for &(inner, amount) in &self.contents[bag] {
+            let inside = self.count_inside_index(inner, counts)?;
+            count = inside.checked_add(1)
+                .and_then(|bags| bags.checked_mul(amount as u64))
+                .and_then(|bags| bags.checked_add(count))?;
+        }
+        counts[bag] = Some(count);
+        Some(count)
+    }
+}
+
+#[cfg(test)]
+fn rules_from(input: &str) -> Vec<Rule<'_>> {
+    input
+        .split('\n')
+        .enumerate()
+        .map(|(index, line)| {
+            let (outer_bag, inner_bags) = crate::parse_line(line).unwrap();
+            Rule { line_number: index + 1, outer_bag, inner_bags }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+const EXAMPLE_RULES: &str = "light red bags contain 1 bright white bag, 2 muted yellow bags.
+dark orange bags contain 3 bright white bags, 4 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+dark olive bags contain 3 faded blue bags, 4 dotted black bags.
+vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
+faded blue bags contain no other bags.
+dotted black bags contain no other bags.";
+
+#[test]
+fn test_containers_of() {
+    let rules = rules_from(EXAMPLE_RULES);
+    let graph = BagGraph::new(&rules).unwrap();
+    let can_bag_contain = |outer: &str, target: &str| graph.containers_of(target).unwrap().contains(&outer);
+
+    assert!(can_bag_contain("vibrant plum", "faded blue"));
+    assert!(!can_bag_contain("vibrant plum", "light red"));
+    assert!(can_bag_contain("light red", "faded blue"));
+
+    assert!(can_bag_contain("bright white", "shiny gold"));
+    assert!(can_bag_contain("muted yellow", "shiny gold"));
+    assert!(can_bag_contain("dark orange", "shiny gold"));
+    assert!(can_bag_contain("light red", "shiny gold"));
+
+    assert_eq!(graph.containers_of("shiny gold"), Some(vec!["bright white", "muted yellow", "light red", "dark orange"]));
+    assert_eq!(graph.containers_of("light red"), Some(vec![]));
+    assert_eq!(graph.containers_of("plaid green"), None);
+}
+
+#[test]
+fn test_count_inside() {
+    let rules = rules_from(EXAMPLE_RULES);
+    let graph = BagGraph::new(&rules).unwrap();
+    assert_eq!(graph.count_inside("faded blue"), Some(Ok(0)));
+    assert_eq!(graph.count_inside("vibrant plum"), Some(Ok(11)));
+    assert_eq!(graph.count_inside("shiny gold"), Some(Ok(32)));
+    assert_eq!(graph.count_inside("plaid green"), None);
+
+    // A bag n levels up holds 1000 + 1000^2 + ... + 1000^n bags, which stops fitting at n = 7
+    let lines: Vec<String> = (0..12)
+        .map(|level| format!("level{} red bags contain 1000 level{} red bags.", level, level + 1))
+        .chain(std::iter::once(String::from("level12 red bags contain no other bags.")))
+        .collect();
+    let input = lines.join("\n");
+    let rules = rules_from(&input);
+    let graph = BagGraph::new(&rules).unwrap();
+    assert_eq!(graph.count_inside("level7 red"), Some(Ok(1_001_001_001_001_000)));
+    assert_eq!(graph.count_inside("level6 red"), Some(Ok(1_001_001_001_001_001_000)));
+    assert_eq!(graph.count_inside("level0 red"), Some(Err(format!("a 'level0 red' bag holds more than {} bags", u64::MAX))));
+}
+
+#[test]
+fn test_invalid_rules() {
+    let rules = rules_from("light red bags contain 1 bright white bag, 2 plaid green bags.
+bright white bags contain 1 shiny gold bag.
+shiny gold bags contain 2 light red bags.
+bright white bags contain no other bags.
+faded blue bags contain 3 faded blue bags.");
+    assert_eq!(BagGraph::new(&rules).err(), Some(vec![
+        (1, String::from("'plaid green' has no rule of its own")),
+        (3, String::from("'light red' ends up inside itself: light red -> bright white -> shiny gold -> light red")),
+        (4, String::from("'bright white' already has a rule on line 2")),
+        (5, String::from("'faded blue' ends up inside itself: faded blue -> faded blue")),
+    ]));
+}
+
+#[test]
+fn test_to_dot() {
+    let rules = rules_from("light red bags contain 1 bright white bag, 2 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 9 faded blue bags.
+shiny gold bags contain 2 faded blue bags.
+faded blue bags contain no other bags.");
+    let graph = BagGraph::new(&rules).unwrap();
+    assert_eq!(graph.to_dot(Selection::Inside("shiny gold")), Some(String::from(concat!(
+        "digraph bags {\n",
+        "    \"shiny gold\";\n",
+        "    \"faded blue\";\n",
+        "    \"shiny gold\" -> \"faded blue\" [label=\"2\"];\n",
+        "}\n",
+    ))));
+    assert_eq!(graph.to_dot(Selection::Containing("shiny gold")), Some(String::from(concat!(
+        "digraph bags {\n",
+        "    \"shiny gold\";\n",
+        "    \"bright white\";\n",
+        "    \"light red\";\n",
+        "    \"bright white\" -> \"shiny gold\" [label=\"1\"];\n",
+        "    \"light red\" -> \"bright white\" [label=\"1\"];\n",
+        "}\n",
+    ))));
+    assert_eq!(graph.to_dot(Selection::All).unwrap().lines().count(), 2 + 5 + 5);
+    assert_eq!(graph.to_dot(Selection::Inside("plaid green")), None);
+    assert_eq!(dot_id("say \"hi\""), "\"say \\\"hi\\\"\"");
+}
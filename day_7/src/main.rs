@@ -1,5 +1,7 @@
 use std::env;
-use std::collections::HashMap;
+use graph::{BagGraph, Selection};
+
+mod graph;
 
 mod file_reader {
     use std::fs::File;
@@ -11,9 +13,9 @@ mod file_reader {
         Ok(string)
     }
 
-    pub fn split_input(string: &String) -> Vec<&str> {
+    pub fn split_input(string: &str) -> Vec<&str> {
         let strings: Vec<&str> = string
-            .split("\n")
+            .split('\n')
             .collect();
         strings
     }
@@ -30,8 +32,17 @@ struct Bag<'a> {
     amount: u32
 }
 
-fn parse_line(line: &str) -> (&str, Vec<Bag>) {
-    let outer_bag_index = line.find(BAGS_CONTAIN_EXPRESSION).unwrap();
+// A rule as it appears in the input, numbered from 1
+struct Rule<'a> {
+    line_number: usize,
+    outer_bag: &'a str,
+    inner_bags: Vec<Bag<'a>>
+}
+
+fn parse_line(line: &str) -> Result<(&str, Vec<Bag<'_>>), String> {
+    let outer_bag_index = line
+        .find(BAGS_CONTAIN_EXPRESSION)
+        .ok_or_else(|| format!("expected '<colour>{}...', got '{}'", BAGS_CONTAIN_EXPRESSION, line))?;
     let (outer_bag, line) = line.split_at(outer_bag_index);
     let mut inner_bags: Vec<Bag> = Vec::new();
     if !line.contains(NO_BAGS_EXPRESSION) {
@@ -39,113 +50,103 @@ fn parse_line(line: &str) -> (&str, Vec<Bag>) {
         let bags: Vec<&str> = line
             .split(", ")
             .map(|string| string
-                .trim_end_matches(".")
+                .trim_end_matches('.')
                 .trim_end_matches(BAG_ENDING_EXPRESSION)
                 .trim_end_matches(BAGS_ENDING_EXPRESSION))
             .collect();
 
         for bag in bags {
-            if let Some(space_index) = bag.find(" ") {
-                let (amount, bag_type) = bag.split_at(space_index);
-                inner_bags.push(Bag{bag_type: bag_type.trim(), amount: amount.parse().unwrap()});
-            }
-        }
-    }
-    (outer_bag, inner_bags)
-}
-
-fn can_bag_contain(outer_bag_type: &str, target_bag_type: &str, bags_map: &HashMap<&str, Vec<Bag>>) -> bool {
-    let bags = &bags_map[outer_bag_type];
-    for bag in bags {
-        if bag.bag_type == target_bag_type {
-            return true
-        } else if can_bag_contain(bag.bag_type, target_bag_type, bags_map) {
-            return true
-        }
-    }
-    false
-}
-
-fn count_bags_that_can_contain(target_bag: &str, bags_map: &HashMap<&str, Vec<Bag>>) -> u32 {
-    let mut count: u32 = 0;
-
-    for (outer_bag, _) in bags_map {
-        if can_bag_contain(outer_bag, target_bag, bags_map) {
-            count += 1;
+            let (amount, bag_type) = bag
+                .split_once(' ')
+                .ok_or_else(|| format!("expected '<amount> <colour> bags', got '{}'", bag))?;
+            let amount = amount
+                .parse()
+                .map_err(|_| format!("invalid amount '{}' of '{}'", amount, bag_type.trim()))?;
+            inner_bags.push(Bag{bag_type: bag_type.trim(), amount});
         }
     }
-
-    count
+    Ok((outer_bag, inner_bags))
 }
 
-fn recurse_bags(initial_bag: &Bag, bags_map: &HashMap<&str, Vec<Bag>>) -> u32 {
-    let bags_in_initial = &bags_map[initial_bag.bag_type];
-    let mut count: u32 = 1;
-
-    for bag in bags_in_initial {
-        count += recurse_bags(&bag, bags_map);
-    }
-
-    count * initial_bag.amount
+#[test]
+fn test_parse_line() {
+    let (outer_bag, inner_bags) = parse_line("light red bags contain 1 bright white bag, 2 muted yellow bags.").unwrap();
+    assert_eq!(outer_bag, "light red");
+    let inner_bags: Vec<(&str, u32)> = inner_bags.iter().map(|bag| (bag.bag_type, bag.amount)).collect();
+    assert_eq!(inner_bags, [("bright white", 1), ("muted yellow", 2)]);
+    assert!(parse_line("faded blue bags contain no other bags.").unwrap().1.is_empty());
+
+    assert_eq!(parse_line("light red bags hold 1 bright white bag.").err(), Some(String::from(
+        "expected '<colour> bags contain ...', got 'light red bags hold 1 bright white bag.'"
+    )));
+    assert_eq!(parse_line("light red bags contain one bright white bag.").err(), Some(String::from(
+        "invalid amount 'one' of 'bright white'"
+    )));
+    assert_eq!(parse_line("light red bags contain bags.").err(), Some(String::from(
+        "expected '<amount> <colour> bags', got 'bags'"
+    )));
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     let path = &args[1];
 
+    let mut dot: Option<&str> = None;
+    let mut colour = "shiny gold";
+    let mut iter = args.iter().skip(2);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--dot" => dot = Some(iter.next().expect("--dot needs all, inside or containing").as_str()),
+            "--colour" => colour = iter.next().expect("--colour needs a colour"),
+            _ => panic!("Unknown argument: {}", arg),
+        }
+    }
+
     let input = file_reader::read_file(path).unwrap();
     let input = file_reader::split_input(&input);
-    let mut iter = input.iter();
-    let mut bags: HashMap<&str, Vec<Bag>> = HashMap::new();
-    while let Some(line) = &iter.next() {
-        let (outer_bag, inner_bags) = parse_line(&line);
-        bags.insert(outer_bag, inner_bags);
+    let mut rules: Vec<Rule> = Vec::new();
+    let mut errors: Vec<(usize, String)> = Vec::new();
+    for (index, line) in input.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue
+        }
+        match parse_line(line) {
+            Ok((outer_bag, inner_bags)) => rules.push(Rule { line_number: index + 1, outer_bag, inner_bags }),
+            Err(error) => errors.push((index + 1, error)),
+        }
+    }
+    // Malformed lines are reported along with the problems of the rules that did parse
+    let graph = match BagGraph::new(&rules) {
+        Ok(graph) if errors.is_empty() => graph,
+        result => {
+            errors.extend(result.err().unwrap_or_default());
+            errors.sort_by_key(|&(line_number, _)| line_number);
+            let errors: Vec<String> = errors
+                .into_iter()
+                .map(|(line_number, error)| format!("line {}: {}", line_number, error))
+                .collect();
+            panic!("Invalid rules:\n{}", errors.join("\n"))
+        },
+    };
+
+    // The graph replaces the answers, so it can be piped straight into Graphviz
+    if let Some(dot) = dot {
+        let selection = match dot {
+            "all" => Selection::All,
+            "inside" => Selection::Inside(colour),
+            "containing" => Selection::Containing(colour),
+            _ => panic!("Unknown selection: {}, expected all, inside or containing", dot),
+        };
+        let output = graph.to_dot(selection)
+            .unwrap_or_else(|| panic!("No rule mentions '{}'", colour));
+        print!("{}", output);
+        return
     }
 
-    let part_1 = count_bags_that_can_contain("shiny gold", &bags);
+    let part_1 = graph.containers_of("shiny gold").map_or(0, |containers| containers.len());
     println!("part 1: {:?}", part_1);
-    let part_2 = recurse_bags(&Bag{bag_type: "shiny gold", amount: 1}, &bags) - 1;
+    let part_2 = graph.count_inside("shiny gold")
+        .unwrap_or(Ok(0))
+        .unwrap_or_else(|error| panic!("{}", error));
     println!("part 2: {:?}", part_2);
 }
-
-#[test]
-fn test_can_bag_contain() {
-    let mut test_map: HashMap<&str, Vec<Bag>> = HashMap::new();
-    test_map.insert("light red", vec![Bag{bag_type: "bright white", amount: 1}, Bag{bag_type: "muted yellow", amount: 2}]);
-    test_map.insert("dark orange", vec![Bag{bag_type: "bright white", amount: 3}, Bag{bag_type: "muted yellow", amount: 4}]);
-    test_map.insert("bright white", vec![Bag{bag_type: "shiny gold", amount: 1}]);
-    test_map.insert("muted yellow", vec![Bag{bag_type: "shiny gold", amount: 2}, Bag{bag_type: "faded blue", amount: 9}]);
-    test_map.insert("shiny gold", vec![Bag{bag_type: "dark olive", amount: 1}, Bag{bag_type: "vibrant plum", amount: 2}]);
-    test_map.insert("dark olive", vec![Bag{bag_type: "faded blue", amount: 3}, Bag{bag_type: "dotted black", amount: 4}]);
-    test_map.insert("vibrant plum", vec![Bag{bag_type: "faded blue", amount: 5}, Bag{bag_type: "dotted black", amount: 6}]);
-    test_map.insert("faded blue", vec![]);
-    test_map.insert("dotted black", vec![]);
-
-    assert_eq!(can_bag_contain("vibrant plum", "faded blue", &test_map), true);
-    assert_eq!(can_bag_contain("vibrant plum", "light red", &test_map), false);
-    assert_eq!(can_bag_contain("light red", "faded blue", &test_map), true);
-
-    assert_eq!(can_bag_contain("bright white", "shiny gold", &test_map), true);
-    assert_eq!(can_bag_contain("muted yellow", "shiny gold", &test_map), true);
-    assert_eq!(can_bag_contain("dark orange", "shiny gold", &test_map), true);
-    assert_eq!(can_bag_contain("light red", "shiny gold", &test_map), true);
-}
-
-#[test]
-fn test_recurse_bags() {
-    let mut test_map: HashMap<&str, Vec<Bag>> = HashMap::new();
-     test_map.insert("light red", vec![Bag{bag_type: "bright white", amount: 1}, Bag{bag_type: "muted yellow", amount: 2}]);
-    test_map.insert("dark orange", vec![Bag{bag_type: "bright white", amount: 3}, Bag{bag_type: "muted yellow", amount: 4}]);
-    test_map.insert("bright white", vec![Bag{bag_type: "shiny gold", amount: 1}]);
-    test_map.insert("muted yellow", vec![Bag{bag_type: "shiny gold", amount: 2}, Bag{bag_type: "faded blue", amount: 9}]);
-    test_map.insert("shiny gold", vec![Bag{bag_type: "dark olive", amount: 1}, Bag{bag_type: "vibrant plum", amount: 2}]);
-    test_map.insert("dark olive", vec![Bag{bag_type: "faded blue", amount: 3}, Bag{bag_type: "dotted black", amount: 4}]);
-    test_map.insert("vibrant plum", vec![Bag{bag_type: "faded blue", amount: 5}, Bag{bag_type: "dotted black", amount: 6}]);
-    test_map.insert("faded blue", vec![]);
-    test_map.insert("dotted black", vec![]);
-
-    assert_eq!(recurse_bags(&Bag{bag_type: "faded blue", amount: 1}, &test_map), 1);
-    assert_eq!(recurse_bags(&Bag{bag_type: "faded blue", amount: 2}, &test_map), 2);
-    assert_eq!(recurse_bags(&Bag{bag_type: "vibrant plum", amount: 1}, &test_map), 12);
-    assert_eq!(recurse_bags(&Bag{bag_type: "vibrant plum", amount: 2}, &test_map), 24);
-}